@@ -0,0 +1,287 @@
+use crate::event::{self, Event, EventKind};
+use crate::room::Room;
+use crate::uno::Uno;
+use actix_web::{web, HttpRequest, Responder};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::Mutex;
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub room_id: u32,
+    pub room: Room,
+}
+
+#[derive(Deserialize)]
+pub struct SeedReq {
+    pub seed: u64,
+}
+
+#[derive(Serialize)]
+struct ImportRes {
+    pub room_id: u32,
+}
+
+fn authorize(req: &HttpRequest) -> Result<(), actix_web::Error> {
+    let token = match env::var("UNO_ADMIN_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => return Err(actix_web::error::ErrorForbidden("Admin is disabled")),
+    };
+
+    let given = req
+        .headers()
+        .get("x-admin-token")
+        .and_then(|value| value.to_str().ok());
+
+    if given != Some(token.as_str()) {
+        return Err(actix_web::error::ErrorUnauthorized("Wrong admin token"));
+    }
+
+    Ok(())
+}
+
+pub async fn export_room(data: web::Data<Mutex<Uno>>, req: HttpRequest) -> impl Responder {
+    authorize(&req)?;
+    let room_id = req
+        .match_info()
+        .get("room_id")
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+    let context = data.lock().unwrap();
+
+    match context.rooms.get(&room_id) {
+        Some(room) => Ok(web::Json(Snapshot {
+            version: SNAPSHOT_VERSION,
+            room_id,
+            room: room.clone(),
+        })),
+        None => Err(actix_web::error::ErrorNotFound("Room does not exist")),
+    }
+}
+
+pub async fn import_room(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    form: web::Json<Snapshot>,
+) -> impl Responder {
+    authorize(&req)?;
+
+    if form.version != SNAPSHOT_VERSION {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Unsupported snapshot version",
+        ));
+    }
+
+    let mut context = data.lock().unwrap();
+    let room = form.into_inner().room;
+
+    for player in room.players.iter() {
+        context.user_index = context.user_index.max(player.id);
+
+        if !context.users.contains_key(&player.id) {
+            let mut user = player.clone();
+            user.hand = vec![];
+            user.drawed = false;
+            context.users.insert(user.id, user);
+            context.save_user(player.id);
+        }
+    }
+
+    context.room_index += 1;
+    let room_id = context.room_index;
+    context.rooms.insert(room_id, room.clone());
+    context.save_room(
+        room_id,
+        vec![Event::new(
+            0,
+            EventKind::Import {
+                room: Box::new(room),
+            },
+        )],
+    );
+
+    Ok(web::Json(ImportRes { room_id }))
+}
+
+pub async fn events(data: web::Data<Mutex<Uno>>, req: HttpRequest) -> impl Responder {
+    authorize(&req)?;
+    let room_id = req
+        .match_info()
+        .get("room_id")
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+    let context = data.lock().unwrap();
+
+    match context.storage.events(room_id) {
+        Ok(events) => Ok(web::Json(events)),
+        Err(error) => Err(actix_web::error::ErrorInternalServerError(error.0)),
+    }
+}
+
+pub async fn replay(data: web::Data<Mutex<Uno>>, req: HttpRequest) -> impl Responder {
+    authorize(&req)?;
+    let room_id = req
+        .match_info()
+        .get("room_id")
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+    let step = req
+        .match_info()
+        .get("step")
+        .unwrap()
+        .parse::<usize>()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid step"))?;
+    let context = data.lock().unwrap();
+
+    let events = context
+        .storage
+        .events(room_id)
+        .map_err(|error| actix_web::error::ErrorInternalServerError(error.0))?;
+
+    if step > events.len() {
+        return Err(actix_web::error::ErrorNotFound("Step out of range"));
+    }
+
+    Ok(web::Json(event::replay(&events[..step])))
+}
+
+pub async fn seed(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    form: web::Json<SeedReq>,
+) -> impl Responder {
+    authorize(&req)?;
+    let room_id = req
+        .match_info()
+        .get("room_id")
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+    let mut context = data.lock().unwrap();
+
+    let room = match context.rooms.get_mut(&room_id) {
+        Some(room) => room,
+        None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+    };
+
+    if room.active {
+        return Err(actix_web::error::ErrorBadRequest("Game already started"));
+    }
+
+    room.seed = form.seed;
+    let event = Event::new(0, EventKind::Seed { seed: form.seed });
+    context.save_room(room_id, vec![event]);
+
+    Ok("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    const TOKEN: &str = "secret";
+
+    // Every test sets the same token, so they can run side by side.
+    fn context() -> web::Data<Mutex<Uno>> {
+        env::set_var("UNO_ADMIN_TOKEN", TOKEN);
+        let mut uno = Uno::new(Box::new(MemoryStorage::new()));
+        uno.rooms.insert(1, Room::new());
+        uno.room_index = 1;
+        web::Data::new(Mutex::new(uno))
+    }
+
+    macro_rules! app {
+        ($data:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data($data.clone())
+                    .route("/api/admin/rooms/{room_id}", web::get().to(export_room))
+                    .route("/api/admin/rooms", web::post().to(import_room)),
+            )
+            .await
+        };
+    }
+
+    fn snapshot(version: u32) -> Snapshot {
+        Snapshot {
+            version,
+            room_id: 1,
+            room: Room::new(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn a_missing_or_wrong_token_is_refused() {
+        let data = context();
+        let app = app!(data);
+
+        let missing = test::TestRequest::get()
+            .uri("/api/admin/rooms/1")
+            .to_request();
+        let wrong = test::TestRequest::get()
+            .uri("/api/admin/rooms/1")
+            .insert_header(("x-admin-token", "guess"))
+            .to_request();
+        let import = test::TestRequest::post()
+            .uri("/api/admin/rooms")
+            .set_json(snapshot(SNAPSHOT_VERSION))
+            .to_request();
+
+        for request in [missing, wrong, import] {
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        assert_eq!(data.lock().unwrap().rooms.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn the_right_token_exports_a_snapshot() {
+        let data = context();
+        let app = app!(data);
+
+        let request = test::TestRequest::get()
+            .uri("/api/admin/rooms/1")
+            .insert_header(("x-admin-token", TOKEN))
+            .to_request();
+        let snapshot: Snapshot = test::call_and_read_body_json(&app, request).await;
+
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.room_id, 1);
+    }
+
+    #[actix_web::test]
+    async fn snapshots_of_another_version_are_refused() {
+        let data = context();
+        let app = app!(data);
+
+        let request = test::TestRequest::post()
+            .uri("/api/admin/rooms")
+            .insert_header(("x-admin-token", TOKEN))
+            .set_json(snapshot(SNAPSHOT_VERSION + 1))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(data.lock().unwrap().rooms.len(), 1);
+
+        let request = test::TestRequest::post()
+            .uri("/api/admin/rooms")
+            .insert_header(("x-admin-token", TOKEN))
+            .set_json(snapshot(SNAPSHOT_VERSION))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(data.lock().unwrap().rooms.len(), 2);
+    }
+}
@@ -0,0 +1,98 @@
+use actix_files::{Files, NamedFile};
+use actix_web::dev::{fn_service, ServiceRequest, ServiceResponse};
+use actix_web::HttpResponse;
+use std::env;
+use std::path::PathBuf;
+
+// The frontend lives in UNO_STATIC_DIR, ./static by default. Nothing is served
+// when the directory has no index.html.
+pub fn directory() -> Option<PathBuf> {
+    let dir = env::var("UNO_STATIC_DIR").unwrap_or_else(|_| "static".to_string());
+    let dir = PathBuf::from(dir);
+
+    if dir.join("index.html").is_file() {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+// Paths that are not files fall back to index.html so the frontend's own
+// routes survive a reload. Unknown /api/ paths still get a 404.
+pub fn files(dir: PathBuf) -> Files {
+    let index = dir.join("index.html");
+
+    Files::new("/", dir)
+        .index_file("index.html")
+        .default_handler(fn_service(move |req: ServiceRequest| {
+            let index = index.clone();
+
+            async move {
+                let (req, _) = req.into_parts();
+
+                if req.path().starts_with("/api/") {
+                    let res = HttpResponse::NotFound().finish();
+                    return Ok(ServiceResponse::new(req, res));
+                }
+
+                let res = NamedFile::open_async(index).await?.into_response(&req);
+                Ok(ServiceResponse::new(req, res))
+            }
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_and_read_body, call_service, init_service, TestRequest};
+    use actix_web::{web, App};
+    use std::fs;
+
+    fn frontend(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(name);
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::write(dir.join("index.html"), "<html>uno</html>").unwrap();
+        fs::write(dir.join("assets").join("app.js"), "start()").unwrap();
+        dir
+    }
+
+    // The only test touching UNO_STATIC_DIR, so nothing else races on it.
+    #[test]
+    fn the_directory_needs_an_index() {
+        let dir = frontend("uno-static-index");
+        env::set_var("UNO_STATIC_DIR", &dir);
+        assert_eq!(directory(), Some(dir.clone()));
+
+        fs::remove_file(dir.join("index.html")).unwrap();
+        assert_eq!(directory(), None);
+    }
+
+    #[actix_web::test]
+    async fn files_routes_and_the_api_are_served_apart() {
+        let dir = frontend("uno-static-files");
+        let app = init_service(
+            App::new()
+                .route("/api/", web::get().to(|| async { "api" }))
+                .service(files(dir)),
+        )
+        .await;
+
+        let get = |path: &str| TestRequest::get().uri(path).to_request();
+
+        let body = call_and_read_body(&app, get("/assets/app.js")).await;
+        assert_eq!(body, "start()");
+
+        let body = call_and_read_body(&app, get("/")).await;
+        assert_eq!(body, "<html>uno</html>");
+
+        let body = call_and_read_body(&app, get("/rooms/3")).await;
+        assert_eq!(body, "<html>uno</html>");
+
+        let body = call_and_read_body(&app, get("/api/")).await;
+        assert_eq!(body, "api");
+
+        let res = call_service(&app, get("/api/nope")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,612 @@
+use rust_uno::card::{all, Card, CardColor, GameState};
+use rust_uno::engine::{Action, Engine, LegalMoves};
+use rust_uno::room::{Room, RoomView};
+use rust_uno::strategy::{BotKind, GameView, Strategy};
+use rust_uno::user::User;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+const USAGE: &str = "Usage: client [--server URL] NAME [ROOM]
+       client --local SEAT SEAT...
+
+Creates a new room, or joins ROOM, on the server at URL
+(http://localhost:8080 by default) and plays from the terminal.
+
+With --local the whole game runs in this process without a server. Each SEAT
+is `random` or `heuristic` for a bot, or the name of a human sharing the
+terminal.";
+
+const HELP: &str = "start | play N [red|green|blue|yellow|pink|teal|orange|purple] | draw \
+                    | pass | penalty | bot random|heuristic | quit";
+
+#[derive(Deserialize)]
+struct Game {
+    pub room: RoomView,
+    pub player: User,
+    pub moves: LegalMoves,
+}
+
+#[derive(Deserialize)]
+struct NewRoomRes {
+    pub room_id: u32,
+}
+
+enum Input {
+    Line(String),
+    Update,
+    Closed,
+}
+
+struct Client {
+    agent: ureq::Agent,
+    server: String,
+    room_id: u32,
+}
+
+impl Client {
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/{}", self.server, path)
+    }
+
+    fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T, String> {
+        self.agent
+            .get(&self.url(path))
+            .call()
+            .map_err(message)?
+            .into_json()
+            .map_err(|e| e.to_string())
+    }
+
+    fn post(&self, path: &str, body: Value) -> Result<String, String> {
+        self.agent
+            .post(&self.url(path))
+            .send_json(body)
+            .map_err(message)?
+            .into_string()
+            .map_err(|e| e.to_string())
+    }
+
+    fn room(&self) -> Result<RoomView, String> {
+        self.get(&format!("room/{}", self.room_id))
+    }
+
+    // GET /api/play deals the cards when the room has not started, so it is
+    // only asked once the game is running or when the player says `start`.
+    fn game(&self) -> Result<Game, String> {
+        self.get(&format!("play/{}", self.room_id))
+    }
+
+    fn run(&self, command: Command) -> Result<(), String> {
+        let room_id = self.room_id;
+        let (path, body) = match command {
+            Command::Start => return self.game().map(|_| ()),
+            Command::Bot(kind) => ("add-bot", json!({ "kind": kind })),
+            Command::Act(Action::Play { card_id, color }) => {
+                ("turn", json!({ "card_id": card_id, "color": color }))
+            }
+            Command::Act(Action::Draw) => ("draw", json!({})),
+            Command::Act(Action::Pass) => ("pass", json!({})),
+            Command::Act(Action::Penalty) => ("penalty", json!({})),
+        };
+
+        self.post(&format!("{}/{}", path, room_id), body)
+            .map(|_| ())
+    }
+}
+
+enum Command {
+    Start,
+    Act(Action),
+    Bot(&'static str),
+}
+
+// Cards are picked by their position in the hand as shown, starting at 1.
+fn parse(line: &str, hand: &[(u8, Card)]) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["start"] => Ok(Command::Start),
+        ["play", index] | ["play", index, _] => {
+            let index: usize = index.parse().map_err(|_| "Not a card number")?;
+            let (card_id, card) = hand
+                .get(index.wrapping_sub(1))
+                .ok_or("You don't have that card")?;
+            let color = match words.get(2) {
+                Some(name) => Some(color(name).ok_or("Unknown color")?),
+                None if card.is_color_card() => return Err("You must choose a color".into()),
+                None => None,
+            };
+            Ok(Command::Act(Action::Play {
+                card_id: *card_id,
+                color,
+            }))
+        }
+        ["draw"] => Ok(Command::Act(Action::Draw)),
+        ["pass"] => Ok(Command::Act(Action::Pass)),
+        ["penalty"] => Ok(Command::Act(Action::Penalty)),
+        ["bot", "random"] => Ok(Command::Bot("Random")),
+        ["bot", "heuristic"] => Ok(Command::Bot("Heuristic")),
+        ["bot", _] => Err("Unknown bot".into()),
+        _ => Err(HELP.into()),
+    }
+}
+
+fn message(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(_, response) => response.into_string().unwrap_or_default(),
+        error => error.to_string(),
+    }
+}
+
+fn color(name: &str) -> Option<CardColor> {
+    match name {
+        "red" => Some(CardColor::Red),
+        "green" => Some(CardColor::Green),
+        "blue" => Some(CardColor::Blue),
+        "yellow" => Some(CardColor::Yellow),
+        "pink" => Some(CardColor::Pink),
+        "teal" => Some(CardColor::Teal),
+        "orange" => Some(CardColor::Orange),
+        "purple" => Some(CardColor::Purple),
+        _ => None,
+    }
+}
+
+fn paint(color: &Option<CardColor>, text: String) -> String {
+    let code = match color {
+        Some(CardColor::Red) => "31",
+        Some(CardColor::Green) => "32",
+        Some(CardColor::Blue) => "34",
+        Some(CardColor::Yellow) => "33",
+        Some(CardColor::Pink) => "95",
+        Some(CardColor::Teal) => "36",
+        Some(CardColor::Orange) => "91",
+        Some(CardColor::Purple) => "35",
+        None => "1",
+    };
+
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+fn label(card: &Card) -> String {
+    let text = match card {
+        Card::Number(number, color) => format!("{} {}", number, color),
+        Card::Stop(color) => format!("stop {}", color),
+        Card::Reverse(color) => format!("reverse {}", color),
+        Card::Plus2(color) => format!("+2 {}", color),
+        Card::ChangeColor => "wild".to_string(),
+        Card::Plus4 => "+4".to_string(),
+        Card::Flip(color) => format!("flip {}", color),
+        Card::DrawFive(color) => format!("+5 {}", color),
+        Card::SkipEveryone(color) => format!("skip everyone {}", color),
+        Card::WildDrawColor => "wild draw color".to_string(),
+        Card::DiscardAll(color) => format!("discard all {}", color),
+        Card::Plus6 => "+6".to_string(),
+        Card::Plus10 => "+10".to_string(),
+        Card::ReversePlus4 => "reverse +4".to_string(),
+        Card::Custom(name, Some(color)) => format!("{} {}", name.to_lowercase(), color),
+        Card::Custom(name, None) => name.to_lowercase(),
+    };
+
+    paint(&card.clone().get_color(), text)
+}
+
+fn render_lobby(room_id: u32, me: u32, room: &RoomView) {
+    print!("\x1b[2J\x1b[H");
+    println!("Room {} - waiting to start\n", room_id);
+
+    for player in room.players.iter() {
+        let you = if player.id == me { " (you)" } else { "" };
+        let bot = if player.bot.is_some() { " [bot]" } else { "" };
+        println!("  {}{}{}", player.name, bot, you);
+    }
+
+    println!("\n{}", HELP);
+}
+
+fn render_game(title: &str, game: &Game) {
+    let room = &game.room;
+    let moves = &game.moves;
+    print!("\x1b[2J\x1b[H");
+    println!("{}\n", title);
+
+    for player in room.players.iter() {
+        let turn = if player.id == room.current_player.id {
+            ">"
+        } else {
+            " "
+        };
+        let you = if player.id == game.player.id {
+            " (you)"
+        } else {
+            ""
+        };
+        let bot = if player.bot.is_some() { " [bot]" } else { "" };
+        let out = if room.eliminated.contains(&player.id) {
+            " [out]"
+        } else {
+            ""
+        };
+        println!(
+            "{} {:<20} {:>3} cards{}{}{}",
+            turn, player.name, player.cards, bot, out, you
+        );
+    }
+
+    let direction = if room.direction { "->" } else { "<-" };
+    println!("\nDirection {}   Deck {}", direction, room.deck_size);
+
+    if let Some((_, top)) = room.board.last() {
+        print!("Top card  {}", label(top));
+        if top.is_color_card() {
+            print!(
+                "   Color {}",
+                paint(&Some(room.color.clone()), room.color.to_string())
+            );
+        }
+        println!();
+    }
+
+    match room.state {
+        Some(GameState::Stop) => println!("Stop pending"),
+        Some(GameState::Plus2) => println!("+2 chain x{}", room.chain_count),
+        Some(GameState::Plus4) => println!("+4 chain x{}", room.chain_count),
+        Some(GameState::DrawFive) => println!("+5 chain x{}", room.chain_count),
+        Some(GameState::DrawColor) => println!("Draw until {}", room.color),
+        Some(GameState::Stack) => println!("+{} stacked", room.pending),
+        None => {}
+    }
+
+    println!("\nYour hand");
+    for (index, (card_id, card)) in game.player.hand.iter().enumerate() {
+        let mark = if moves.playable.contains(card_id) {
+            "*"
+        } else {
+            " "
+        };
+        println!(" {}{:>3}  {}", mark, index + 1, label(card));
+    }
+
+    println!();
+    if room.over {
+        println!("The round is over");
+    } else if room.current_player.id == game.player.id {
+        let mut allowed = vec![];
+        if !moves.playable.is_empty() {
+            allowed.push("play");
+        }
+        if moves.draw {
+            allowed.push("draw");
+        }
+        if moves.pass {
+            allowed.push("pass");
+        }
+        if moves.penalty {
+            allowed.push("penalty");
+        }
+        println!("Your turn: {}", allowed.join(", "));
+    } else {
+        println!("Waiting for {}", room.current_player.name);
+    }
+}
+
+// Redraws the table every time the server says the room changed, and after
+// each command the player types.
+fn watch(server: &str, room_id: u32, sender: Sender<Input>) {
+    let url = format!("{}/api/state/{}", server.replacen("http", "ws", 1), room_id);
+
+    thread::spawn(move || {
+        let mut socket = match tungstenite::connect(url.as_str()) {
+            Ok((socket, _)) => socket,
+            Err(error) => {
+                eprintln!("Could not watch the room: {}", error);
+                return;
+            }
+        };
+
+        while let Ok(message) = socket.read() {
+            if message.is_text() && sender.send(Input::Update).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+fn read_lines(sender: Sender<Input>) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(Input::Line(line)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(Input::Closed);
+    });
+}
+
+fn describe(room: &Room, player_id: u32, action: &Action) -> Result<String, String> {
+    let player = room
+        .players
+        .iter()
+        .find(|player| player.id == player_id)
+        .ok_or("Player not in room")?;
+
+    Ok(match action {
+        Action::Play { card_id, color } => {
+            let (_, card) = player
+                .hand
+                .iter()
+                .find(|(id, _)| id == card_id)
+                .ok_or("You don't have that card")?;
+            match color {
+                Some(color) => {
+                    format!("{} played {} and chose {}", player.name, label(card), color)
+                }
+                None => format!("{} played {}", player.name, label(card)),
+            }
+        }
+        Action::Draw => format!("{} drew a card", player.name),
+        Action::Pass => format!("{} passed", player.name),
+        Action::Penalty => format!("{} took the penalty", player.name),
+    })
+}
+
+fn wait_for_enter(lines: &mut impl Iterator<Item = String>) -> bool {
+    let _ = io::stdout().flush();
+    lines.next().is_some()
+}
+
+// Hot-seat game: bots move on their own, humans take turns on the same
+// terminal and the hand is hidden until the next one is at the keyboard.
+fn local(seats: &[String]) {
+    if seats.len() < 2 {
+        fail();
+    }
+
+    let mut room = Room::new();
+    let mut strategies: HashMap<u32, Box<dyn Strategy>> = HashMap::new();
+
+    for (index, seat) in seats.iter().enumerate() {
+        let id = index as u32 + 1;
+        let kind = match seat.as_str() {
+            "random" => Some(BotKind::Random),
+            "heuristic" => Some(BotKind::Heuristic),
+            _ => None,
+        };
+
+        match kind {
+            Some(kind) => {
+                strategies.insert(id, kind.strategy());
+                room.players
+                    .push(User::bot(format!("Bot {}", id), id, kind));
+            }
+            None => room.players.push(User::new(seat.clone(), id)),
+        }
+    }
+
+    let humans = room.players.len() - strategies.len();
+    if let Err(error) = Engine::deal(&mut room, 1, &all()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines().map_while(Result::ok);
+    let mut log: Vec<String> = vec![];
+    let mut notice = String::new();
+    let mut at_keyboard = None;
+    let mut finished = vec![];
+
+    while !room.is_over() {
+        let player_id = room.current().id;
+
+        let action = if let Some(strategy) = strategies.get_mut(&player_id) {
+            let actions = Engine::legal_actions(&room, player_id);
+            let choice = strategy.choose(&GameView::new(&room, player_id), &actions);
+            if Engine::validate(&room, player_id, &choice).is_ok() {
+                choice
+            } else if let Some(action) = actions.first() {
+                action.clone()
+            } else {
+                eprintln!("{} has no legal move", room.current().name);
+                process::exit(1);
+            }
+        } else {
+            let name = room.current().name.clone();
+
+            if humans > 1 && at_keyboard != Some(player_id) {
+                print!("\x1b[2J\x1b[H");
+                print!("Pass the terminal to {} and press enter", name);
+                if !wait_for_enter(&mut lines) {
+                    return;
+                }
+                at_keyboard = Some(player_id);
+            }
+
+            let game = Game {
+                room: room.view(player_id),
+                player: room.current().clone(),
+                moves: Engine::legal_moves(&room, player_id),
+            };
+            render_game(&format!("Local game - {}", name), &game);
+
+            for line in log.iter().rev().take(5).rev() {
+                println!("{}", line);
+            }
+            if !notice.is_empty() {
+                println!("{}", notice);
+            }
+            print!("> ");
+            let _ = io::stdout().flush();
+
+            let line = match lines.next() {
+                Some(line) => line,
+                None => return,
+            };
+
+            match parse(&line, &game.player.hand) {
+                _ if line.trim() == "quit" => return,
+                Ok(Command::Act(action)) => action,
+                Ok(_) => {
+                    notice = "Only play, draw, pass and penalty work in a local game".into();
+                    continue;
+                }
+                Err(error) => {
+                    notice = error;
+                    continue;
+                }
+            }
+        };
+
+        let line = match describe(&room, player_id, &action) {
+            Ok(line) => line,
+            Err(error) => {
+                notice = error;
+                continue;
+            }
+        };
+        match Engine::apply(&mut room, player_id, action) {
+            Ok(_) => {
+                log.push(line);
+                notice.clear();
+            }
+            Err(error) => notice = error.to_string(),
+        }
+
+        for player in room.players.iter() {
+            if player.hand.is_empty() && !finished.contains(&player.id) {
+                finished.push(player.id);
+                log.push(format!("{} is out of cards", player.name));
+            }
+        }
+    }
+
+    print!("\x1b[2J\x1b[H");
+    for line in log.iter().rev().take(5).rev() {
+        println!("{}", line);
+    }
+    println!("\nThe round is over");
+
+    for (place, player_id) in finished.iter().enumerate() {
+        let player = room.players.iter().find(|p| p.id == *player_id).unwrap();
+        println!("{:>3}. {}", place + 1, player.name);
+    }
+}
+
+fn fail() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut server = "http://localhost:8080".to_string();
+    let mut offline = false;
+    let mut positional = vec![];
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server = args.next().unwrap_or_else(|| fail()),
+            "--local" => offline = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    if offline {
+        local(&positional);
+    } else {
+        online(&server, &positional);
+    }
+}
+
+fn online(server: &str, positional: &[String]) {
+    let server = server.trim_end_matches('/').to_string();
+    let agent = ureq::AgentBuilder::new().build();
+    let mut client = Client {
+        agent,
+        server,
+        room_id: 0,
+    };
+
+    let body = match positional {
+        [name] | [name, _] => json!({ "username": name }),
+        _ => fail(),
+    };
+
+    let joined = match positional.get(1) {
+        Some(room) => {
+            client.room_id = room.parse().unwrap_or_else(|_| fail());
+            client.post(&format!("join-room/{}", client.room_id), body)
+        }
+        None => client
+            .post("new-room", body)
+            .and_then(|res| serde_json::from_str::<NewRoomRes>(&res).map_err(|e| e.to_string()))
+            .map(|res| {
+                client.room_id = res.room_id;
+                String::new()
+            }),
+    };
+
+    if let Err(error) = joined {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+
+    let me: User = client.get("").unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    let (sender, inputs) = channel();
+    watch(&client.server, client.room_id, sender.clone());
+    read_lines(sender.clone());
+    sender.send(Input::Update).unwrap();
+
+    let mut hand = vec![];
+    let mut notice = String::new();
+
+    for input in inputs {
+        match input {
+            Input::Line(line) if line.trim() == "quit" => break,
+            Input::Line(line) if line.trim().is_empty() => {}
+            Input::Line(line) => {
+                notice = match parse(&line, &hand).and_then(|command| client.run(command)) {
+                    Ok(()) => String::new(),
+                    Err(error) => error,
+                }
+            }
+            Input::Update => notice.clear(),
+            Input::Closed => break,
+        }
+
+        let shown = client.room().and_then(|room| {
+            if room.active {
+                let game = client.game()?;
+                render_game(&format!("Room {}", client.room_id), &game);
+                hand = game.player.hand;
+            } else {
+                render_lobby(client.room_id, me.id, &room);
+            }
+            Ok(())
+        });
+
+        if let Err(error) = shown {
+            notice = error;
+        }
+
+        if !notice.is_empty() {
+            println!("{}", notice);
+        }
+        print!("> ");
+        let _ = io::stdout().flush();
+    }
+}
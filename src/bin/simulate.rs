@@ -0,0 +1,103 @@
+use rust_uno::room::RuleSet;
+use rust_uno::sim::{run, Entrant};
+use rust_uno::strategy::{ExternalStrategy, HeuristicStrategy, RandomStrategy, Strategy};
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage: simulate [--games N] [--seed S] STRATEGY STRATEGY...
+
+STRATEGY is `random`, `heuristic` or the command line of an external bot,
+for example \"python3 bots/mybot.py\".";
+
+fn strategy(spec: &str) -> Box<dyn Strategy> {
+    match spec {
+        "random" => Box::new(RandomStrategy),
+        "heuristic" => Box::new(HeuristicStrategy),
+        command => match ExternalStrategy::spawn(command) {
+            Ok(strategy) => Box::new(strategy),
+            Err(error) => {
+                eprintln!("Could not start {}: {}", command, error);
+                process::exit(1);
+            }
+        },
+    }
+}
+
+fn fail() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut games = 1000;
+    let mut seed = rand::random();
+    let mut specs = vec![];
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                games = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or_else(|| fail())
+            }
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_else(|| fail())
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => specs.push(arg),
+        }
+    }
+
+    if specs.len() < 2 {
+        fail();
+    }
+
+    let max = RuleSet::Classic.max_players();
+    if specs.len() > max {
+        eprintln!("The deck can only be dealt to {} players", max);
+        process::exit(1);
+    }
+
+    let mut entrants: Vec<Entrant> = specs
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| Entrant {
+            name: format!("{} {}", i + 1, spec),
+            strategy: strategy(spec),
+        })
+        .collect();
+
+    let report = match run(&mut entrants, games, seed) {
+        Ok(report) => report,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    println!("seed {}", seed);
+    println!("{} games, {} unfinished", report.games, report.unfinished);
+    println!(
+        "average length {:.1} moves (95% CI {:.1} - {:.1})",
+        report.average_moves, report.moves_interval.0, report.moves_interval.1
+    );
+
+    for standing in report.standings {
+        println!(
+            "{:<30} {:>6} wins {:>6.2}% (95% CI {:.2}% - {:.2}%)",
+            standing.name,
+            standing.wins,
+            standing.win_rate * 100.0,
+            standing.interval.0 * 100.0,
+            standing.interval.1 * 100.0
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::engine::{Action, Engine};
+use crate::strategy::{GameView, Strategy};
+use crate::uno::{notify, Uno};
+use actix_web::web;
+use std::sync::Mutex;
+
+const MAX_MOVES: usize = 1000;
+
+struct Turn {
+    player_id: u32,
+    strategy: Box<dyn Strategy>,
+    view: GameView,
+    actions: Vec<Action>,
+}
+
+// The bot whose turn it is, if any, with its strategy taken out of the
+// context while it thinks.
+fn next_turn(context: &mut Uno, room_id: u32) -> Result<Option<Turn>, String> {
+    let room = match context.rooms.get_mut(&room_id) {
+        Some(room) => room,
+        None => return Ok(None),
+    };
+
+    if room.is_over() {
+        for player in room.players.iter() {
+            context.strategies.remove(&player.id);
+        }
+    }
+
+    if !room.active || room.is_over() {
+        return Ok(None);
+    }
+
+    room.update_player();
+    let player = room.current_player.clone();
+
+    let kind = match player.bot {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+
+    let actions = Engine::legal_actions(room, player.id);
+    if actions.is_empty() {
+        return Err(format!("Bot {} has no legal move", player.id));
+    }
+
+    let view = GameView::new(room, player.id);
+    let strategy = context
+        .strategies
+        .remove(&player.id)
+        .unwrap_or_else(|| kind.strategy());
+
+    Ok(Some(Turn {
+        player_id: player.id,
+        strategy,
+        view,
+        actions,
+    }))
+}
+
+// Plays the bots' turns until a person has to move. Strategies can take a
+// while, external ones up to their timeout, so they think on a blocking
+// thread without holding the lock, and the move is checked again after.
+pub async fn run(data: web::Data<Mutex<Uno>>, room_id: u32) {
+    if !data.lock().unwrap().bots_running.insert(room_id) {
+        return;
+    }
+
+    for _ in 0..MAX_MOVES {
+        let turn = {
+            let mut context = data.lock().unwrap();
+            let turn = next_turn(&mut context, room_id);
+
+            // Let go of the room under the same lock that found nothing to
+            // do, so a move made right after starts a new runner.
+            if !matches!(turn, Ok(Some(_))) {
+                context.bots_running.remove(&room_id);
+            }
+
+            turn
+        };
+
+        let turn = match turn {
+            Ok(Some(turn)) => turn,
+            Ok(None) => return,
+            Err(error) => {
+                eprintln!("Room {}: {}", room_id, error);
+                return;
+            }
+        };
+
+        let Turn {
+            player_id,
+            mut strategy,
+            view,
+            actions,
+        } = turn;
+        let thought = web::block(move || {
+            let choice = strategy.choose(&view, &actions);
+            (strategy, choice, actions)
+        })
+        .await;
+
+        let (strategy, choice, actions) = match thought {
+            Ok(thought) => thought,
+            Err(error) => {
+                eprintln!("Room {}: bot {} failed: {}", room_id, player_id, error);
+                break;
+            }
+        };
+
+        let sockets = {
+            let mut context = data.lock().unwrap();
+            context.strategies.insert(player_id, strategy);
+            let room = match context.rooms.get_mut(&room_id) {
+                Some(room) => room,
+                None => break,
+            };
+
+            let events = Engine::apply(room, player_id, choice)
+                .or_else(|_| Engine::apply(room, player_id, actions[0].clone()));
+
+            match events {
+                Ok(events) => context.save_room(room_id, events),
+                Err(error) => {
+                    eprintln!("Room {}: bot {} can't move: {}", room_id, player_id, error);
+                    break;
+                }
+            }
+
+            context.sockets(room_id)
+        };
+
+        notify(sockets).await;
+    }
+
+    data.lock().unwrap().bots_running.remove(&room_id);
+}
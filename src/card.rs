@@ -7,6 +7,10 @@ pub enum CardColor {
     Green,
     Blue,
     Yellow,
+    Pink,
+    Teal,
+    Orange,
+    Purple,
 }
 
 impl Display for CardColor {
@@ -16,11 +20,15 @@ impl Display for CardColor {
             CardColor::Green => f.write_str("green"),
             CardColor::Blue => f.write_str("blue"),
             CardColor::Yellow => f.write_str("yellow"),
+            CardColor::Pink => f.write_str("pink"),
+            CardColor::Teal => f.write_str("teal"),
+            CardColor::Orange => f.write_str("orange"),
+            CardColor::Purple => f.write_str("purple"),
         }
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum Card {
     Number(u8, CardColor),
     Stop(CardColor),
@@ -28,57 +36,98 @@ pub enum Card {
     Plus2(CardColor),
     ChangeColor,
     Plus4,
+    Flip(CardColor),
+    DrawFive(CardColor),
+    SkipEveryone(CardColor),
+    WildDrawColor,
+    DiscardAll(CardColor),
+    Plus6,
+    Plus10,
+    ReversePlus4,
+    // A house card, defined by name in the room's house rules.
+    Custom(String, Option<CardColor>),
 }
 
-#[derive(Clone, Serialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum GameState {
+    // Stop skips the next player right away now. This is kept so rooms
+    // saved while a Stop was passed on can finish.
     Stop,
     Plus2,
     Plus4,
+    DrawFive,
+    DrawColor,
+    Stack,
 }
 
 impl Card {
     pub fn is_stop(&self) -> bool {
-        match self {
-            Card::Stop(_) => true,
-            _ => false,
-        }
+        matches!(self, Card::Stop(_))
     }
 
     pub fn is_reverse(&self) -> bool {
-        match self {
-            Card::Reverse(_) => true,
-            _ => false,
-        }
+        matches!(self, Card::Reverse(_) | Card::ReversePlus4)
     }
 
     pub fn is_plus_2(&self) -> bool {
+        matches!(self, Card::Plus2(_))
+    }
+
+    pub fn is_flip(&self) -> bool {
+        matches!(self, Card::Flip(_))
+    }
+
+    pub fn is_draw_five(&self) -> bool {
+        matches!(self, Card::DrawFive(_))
+    }
+
+    pub fn is_skip_everyone(&self) -> bool {
+        matches!(self, Card::SkipEveryone(_))
+    }
+
+    pub fn is_discard_all(&self) -> bool {
+        matches!(self, Card::DiscardAll(_))
+    }
+
+    // How many cards the next player has to draw.
+    pub fn draws(&self) -> u8 {
         match self {
-            Card::Plus2(_) => true,
-            _ => false,
+            Card::Plus2(_) => 2,
+            Card::Plus4 | Card::ReversePlus4 => 4,
+            Card::DrawFive(_) => 5,
+            Card::Plus6 => 6,
+            Card::Plus10 => 10,
+            _ => 0,
         }
     }
 
     pub fn is_normal(&self) -> bool {
-        match self {
-            Card::Number(_, _) => true,
-            _ => false,
-        }
+        matches!(self, Card::Number(_, _))
     }
 
     pub fn is_color_card(&self) -> bool {
-        *self == Card::Plus4 || *self == Card::ChangeColor
+        matches!(
+            self,
+            Card::Plus4
+                | Card::ChangeColor
+                | Card::WildDrawColor
+                | Card::Plus6
+                | Card::Plus10
+                | Card::ReversePlus4
+                | Card::Custom(_, None)
+        )
     }
 
     pub fn can_chain(&self) -> bool {
-        *self == Card::Plus4 || self.is_plus_2() || self.is_stop()
+        self.draws() > 0 || *self == Card::WildDrawColor
     }
 
     pub fn to_game_state(&self) -> Option<GameState> {
         match self {
-            Card::Stop(_) => Some(GameState::Stop),
             Card::Plus2(_) => Some(GameState::Plus2),
             Card::Plus4 => Some(GameState::Plus4),
+            Card::DrawFive(_) => Some(GameState::DrawFive),
+            Card::WildDrawColor => Some(GameState::DrawColor),
             _ => None,
         }
     }
@@ -89,6 +138,11 @@ impl Card {
             Card::Stop(color) => Some(color),
             Card::Reverse(color) => Some(color),
             Card::Plus2(color) => Some(color),
+            Card::Flip(color) => Some(color),
+            Card::DrawFive(color) => Some(color),
+            Card::SkipEveryone(color) => Some(color),
+            Card::DiscardAll(color) => Some(color),
+            Card::Custom(_, color) => color,
             _ => None,
         }
     }
@@ -109,6 +163,15 @@ impl Card {
             Some(GameState::Plus4) => {
                 make_error(other == Card::Plus4, "You can only chain or pass")
             }
+            Some(GameState::DrawFive) => {
+                make_error(other.is_draw_five(), "You can only chain or pass")
+            }
+            // Draw Color can't be stacked, the cards have to be drawn.
+            Some(GameState::DrawColor) => make_error(false, "You must draw until the color"),
+            Some(GameState::Stack) => make_error(
+                other.draws() >= self.draws(),
+                "You can only stack a draw card as big or bigger",
+            ),
             None => self.free_match(choosen_color, other),
         }
     }
@@ -132,7 +195,32 @@ impl Card {
                 other.is_plus_2() || other.matches_color(color),
                 "Wrong color",
             ),
-            Card::ChangeColor => make_error(
+            Card::Flip(color) => {
+                make_error(other.is_flip() || other.matches_color(color), "Wrong color")
+            }
+            Card::DrawFive(color) => make_error(
+                other.is_draw_five() || other.matches_color(color),
+                "Wrong color",
+            ),
+            Card::SkipEveryone(color) => make_error(
+                other.is_skip_everyone() || other.matches_color(color),
+                "Wrong color",
+            ),
+            Card::DiscardAll(color) => make_error(
+                other.is_discard_all() || other.matches_color(color),
+                "Wrong color",
+            ),
+            Card::Custom(name, Some(color)) => make_error(
+                matches!(&other, Card::Custom(other_name, _) if *other_name == name)
+                    || other.matches_color(color),
+                "Wrong color",
+            ),
+            Card::ChangeColor
+            | Card::Custom(_, None)
+            | Card::WildDrawColor
+            | Card::Plus6
+            | Card::Plus10
+            | Card::ReversePlus4 => make_error(
                 other.matches_color(choosen_color.clone()),
                 format!("Choosen color is {}", choosen_color).as_str(),
             ),
@@ -145,14 +233,14 @@ impl Card {
 }
 
 pub fn all() -> Vec<Card> {
-    let colors = vec![
+    let colors = [
         CardColor::Red,
         CardColor::Green,
         CardColor::Blue,
         CardColor::Yellow,
     ];
 
-    vec![
+    [
         colors
             .iter()
             .map(|color| Card::Number(0, color.clone()))
@@ -178,6 +266,91 @@ pub fn all() -> Vec<Card> {
     .concat()
 }
 
+// The classic deck plus the No Mercy cards.
+pub fn no_mercy() -> Vec<Card> {
+    let colors = [
+        CardColor::Red,
+        CardColor::Green,
+        CardColor::Blue,
+        CardColor::Yellow,
+    ];
+
+    [
+        all(),
+        colors
+            .iter()
+            .flat_map(|color| vec![Card::SkipEveryone(color.clone()); 2])
+            .collect(),
+        colors
+            .iter()
+            .flat_map(|color| vec![Card::DiscardAll(color.clone()); 2])
+            .collect(),
+        vec![Card::Plus6; 4],
+        vec![Card::Plus10; 4],
+        vec![Card::ReversePlus4; 4],
+    ]
+    .concat()
+}
+
+// UNO Flip cards have a light and a dark side. Each pair is one physical
+// card: the first face is shown while the table is light, the second while
+// it is dark. Flip cards are paired by color so the card on top of the
+// discard pile is still a Flip after the table turns over.
+pub fn flip() -> Vec<(Card, Card)> {
+    let light = [
+        CardColor::Red,
+        CardColor::Green,
+        CardColor::Blue,
+        CardColor::Yellow,
+    ];
+    let dark = [
+        CardColor::Pink,
+        CardColor::Teal,
+        CardColor::Orange,
+        CardColor::Purple,
+    ];
+
+    let side = |colors: &[CardColor; 4], actions: &dyn Fn(&CardColor) -> [Card; 3]| {
+        let numbers = (1..10).flat_map(|n| colors.iter().map(move |c| Card::Number(n, c.clone())));
+        let actions = colors.iter().flat_map(actions);
+        numbers.chain(actions).collect::<Vec<Card>>()
+    };
+
+    let light_cards = side(&light, &|color| {
+        [
+            Card::Stop(color.clone()),
+            Card::Reverse(color.clone()),
+            Card::Plus2(color.clone()),
+        ]
+    });
+    let mut dark_cards = side(&dark, &|color| {
+        [
+            Card::SkipEveryone(color.clone()),
+            Card::Reverse(color.clone()),
+            Card::DrawFive(color.clone()),
+        ]
+    });
+
+    // Shifted so the two sides of a card rarely share their rank.
+    dark_cards.rotate_left(17);
+
+    let mut pairs: Vec<(Card, Card)> = light_cards.into_iter().zip(dark_cards).collect();
+    pairs.extend(
+        light
+            .iter()
+            .zip(dark.iter())
+            .flat_map(|(l, d)| vec![(Card::Flip(l.clone()), Card::Flip(d.clone())); 2]),
+    );
+    pairs.extend(vec![
+        (Card::ChangeColor, Card::WildDrawColor),
+        (Card::ChangeColor, Card::WildDrawColor),
+        (Card::Plus4, Card::ChangeColor),
+        (Card::Plus4, Card::ChangeColor),
+    ]);
+
+    pairs
+}
+
 fn make_error(condition: bool, message: &str) -> Option<String> {
     if condition {
         None
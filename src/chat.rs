@@ -0,0 +1,477 @@
+use crate::event::now;
+use crate::uno::{broadcast, room_and_player, Uno};
+use actix_session::Session;
+use actix_web::{web, HttpRequest, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+
+const HISTORY: usize = 200;
+const MAX_LENGTH: usize = 500;
+const RATE_MESSAGES: usize = 5;
+const RATE_WINDOW: u64 = 10_000;
+const REACTION_COOLDOWN: u64 = 3_000;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ChatMessage {
+    pub at: u64,
+    pub player_id: u32,
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum Reaction {
+    Nice,
+    Oops,
+    UnoIncoming,
+    GoodGame,
+    Hurry,
+    Wow,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ReactionMessage {
+    pub at: u64,
+    pub player_id: u32,
+    pub name: String,
+    pub reaction: Reaction,
+}
+
+// Saved with the room on every message and mute, so a restart loses none.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Chat {
+    pub messages: Vec<ChatMessage>,
+    pub muted: Vec<u32>,
+    #[serde(skip)]
+    sent: HashMap<u32, Vec<u64>>,
+    #[serde(skip)]
+    reacted: HashMap<u32, u64>,
+}
+
+impl Chat {
+    pub fn post(
+        &mut self,
+        player_id: u32,
+        name: String,
+        text: &str,
+    ) -> Result<ChatMessage, String> {
+        let text = text.trim();
+        let at = now();
+
+        if self.muted.contains(&player_id) {
+            return Err("You are muted".to_string());
+        }
+
+        if text.is_empty() {
+            return Err("Empty message".to_string());
+        }
+
+        if text.chars().count() > MAX_LENGTH {
+            return Err(format!("Messages are limited to {} characters", MAX_LENGTH));
+        }
+
+        let sent = self.sent.entry(player_id).or_default();
+        sent.retain(|time| at.saturating_sub(*time) < RATE_WINDOW);
+
+        if sent.len() >= RATE_MESSAGES {
+            return Err("You are sending messages too fast".to_string());
+        }
+
+        sent.push(at);
+
+        let message = ChatMessage {
+            at,
+            player_id,
+            name,
+            text: censor(text, &banned_words()),
+        };
+
+        self.messages.push(message.clone());
+        if self.messages.len() > HISTORY {
+            self.messages.remove(0);
+        }
+
+        Ok(message)
+    }
+
+    // Reactions are not kept, they only go out on the socket.
+    pub fn react(
+        &mut self,
+        player_id: u32,
+        name: String,
+        reaction: Reaction,
+    ) -> Result<ReactionMessage, String> {
+        let at = now();
+
+        if self.muted.contains(&player_id) {
+            return Err("You are muted".to_string());
+        }
+
+        if let Some(last) = self.reacted.get(&player_id) {
+            if at.saturating_sub(*last) < REACTION_COOLDOWN {
+                return Err("Wait a moment before reacting again".to_string());
+            }
+        }
+
+        self.reacted.insert(player_id, at);
+
+        Ok(ReactionMessage {
+            at,
+            player_id,
+            name,
+            reaction,
+        })
+    }
+
+    pub fn mute(&mut self, player_id: u32, muted: bool) {
+        self.muted.retain(|id| *id != player_id);
+
+        if muted {
+            self.muted.push(player_id);
+        }
+    }
+}
+
+// The filter is set by whoever runs the server:
+// UNO_BANNED_WORDS="word,other word"
+fn banned_words() -> Vec<String> {
+    env::var("UNO_BANNED_WORDS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+// The words of `text` with the byte range each one covers.
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = vec![];
+    let mut start = None;
+
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(from)) => {
+                words.push((from, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    words
+}
+
+// Replaces every banned word or phrase with asterisks, ignoring case. Only
+// whole words match, so banning "ass" leaves "class" alone, and the words of
+// a phrase can be apart by any spaces or punctuation.
+pub fn censor(text: &str, banned: &[String]) -> String {
+    let found = words(text);
+    let phrases: Vec<Vec<&str>> = banned
+        .iter()
+        .map(|phrase| {
+            words(phrase)
+                .into_iter()
+                .map(|(from, to)| &phrase[from..to])
+                .collect::<Vec<&str>>()
+        })
+        .filter(|phrase| !phrase.is_empty())
+        .collect();
+
+    let lowered: Vec<String> = found
+        .iter()
+        .map(|(from, to)| text[*from..*to].to_lowercase())
+        .collect();
+    let mut hidden = vec![false; found.len()];
+
+    for start in 0..found.len() {
+        for phrase in phrases.iter() {
+            let end = start + phrase.len();
+            if end <= found.len() && lowered[start..end].iter().eq(phrase.iter()) {
+                hidden[start..end].iter_mut().for_each(|hide| *hide = true);
+            }
+        }
+    }
+
+    let mut censored = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for ((from, to), hide) in found.iter().zip(hidden) {
+        if hide {
+            censored.push_str(&text[last..*from]);
+            censored.extend(text[*from..*to].chars().map(|_| '*'));
+            last = *to;
+        }
+    }
+
+    censored.push_str(&text[last..]);
+    censored
+}
+
+#[derive(Serialize)]
+struct ChatUpdate<'a> {
+    pub chat: &'a ChatMessage,
+}
+
+#[derive(Serialize)]
+struct ReactionUpdate<'a> {
+    pub reaction: &'a ReactionMessage,
+}
+
+#[derive(Serialize)]
+struct MuteUpdate<'a> {
+    pub muted: &'a [u32],
+}
+
+pub async fn history(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+) -> impl Responder {
+    let context = data.lock().unwrap();
+    let (room_id, player_id) = room_and_player(req, session);
+
+    let room = match context.rooms.get(&room_id) {
+        Some(room) => room,
+        None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+    };
+
+    if !room.players.iter().any(|player| player.id == player_id) {
+        return Err(actix_web::error::ErrorUnauthorized("Player not in room"));
+    }
+
+    Ok(web::Json(room.chat.messages.clone()))
+}
+
+#[derive(Deserialize)]
+pub struct SayReq {
+    pub text: String,
+}
+
+pub async fn say(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<SayReq>,
+) -> impl Responder {
+    let (sockets, update) = {
+        let mut context = data.lock().unwrap();
+        let (room_id, player_id) = room_and_player(req, session);
+
+        let room = match context.rooms.get_mut(&room_id) {
+            Some(room) => room,
+            None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+        };
+
+        let name = match room.players.iter().find(|player| player.id == player_id) {
+            Some(player) => player.name.clone(),
+            None => return Err(actix_web::error::ErrorUnauthorized("Player not in room")),
+        };
+
+        let message = room
+            .chat
+            .post(player_id, name, &form.text)
+            .map_err(actix_web::error::ErrorBadRequest)?;
+
+        let update = serde_json::to_string(&ChatUpdate { chat: &message }).unwrap();
+        context.save_room(room_id, vec![]);
+        (context.sockets(room_id), update)
+    };
+
+    broadcast(sockets, &update).await;
+    Ok("")
+}
+
+#[derive(Deserialize)]
+pub struct ReactReq {
+    pub reaction: Reaction,
+}
+
+pub async fn react(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<ReactReq>,
+) -> impl Responder {
+    let (sockets, update) = {
+        let mut context = data.lock().unwrap();
+        let (room_id, player_id) = room_and_player(req, session);
+
+        let room = match context.rooms.get_mut(&room_id) {
+            Some(room) => room,
+            None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+        };
+
+        let name = match room.players.iter().find(|player| player.id == player_id) {
+            Some(player) => player.name.clone(),
+            None => return Err(actix_web::error::ErrorUnauthorized("Player not in room")),
+        };
+
+        let message = room
+            .chat
+            .react(player_id, name, form.reaction.clone())
+            .map_err(actix_web::error::ErrorBadRequest)?;
+
+        let update = serde_json::to_string(&ReactionUpdate { reaction: &message }).unwrap();
+        (context.sockets(room_id), update)
+    };
+
+    broadcast(sockets, &update).await;
+    Ok("")
+}
+
+#[derive(Deserialize)]
+pub struct MuteReq {
+    pub player_id: u32,
+    pub muted: bool,
+}
+
+pub async fn mute(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<MuteReq>,
+) -> impl Responder {
+    let (sockets, update) = {
+        let mut context = data.lock().unwrap();
+        let (room_id, player_id) = room_and_player(req, session);
+
+        let room = match context.rooms.get_mut(&room_id) {
+            Some(room) => room,
+            None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+        };
+
+        if room.host() != Some(player_id) {
+            return Err(actix_web::error::ErrorUnauthorized(
+                "Only the host can mute",
+            ));
+        }
+
+        if form.player_id == player_id {
+            return Err(actix_web::error::ErrorBadRequest("You can't mute yourself"));
+        }
+
+        if !room
+            .players
+            .iter()
+            .any(|player| player.id == form.player_id)
+        {
+            return Err(actix_web::error::ErrorBadRequest("Player not in room"));
+        }
+
+        room.chat.mute(form.player_id, form.muted);
+
+        let update = serde_json::to_string(&MuteUpdate {
+            muted: &room.chat.muted,
+        })
+        .unwrap();
+        context.save_room(room_id, vec![]);
+        (context.sockets(room_id), update)
+    };
+
+    broadcast(sockets, &update).await;
+    Ok("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn banned(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn censor_hides_whole_words_in_any_case() {
+        let banned = banned(&["ass", "darn"]);
+
+        assert_eq!(censor("Darn it, ASS!", &banned), "**** it, ***!");
+        assert_eq!(censor("a classy pass", &banned), "a classy pass");
+        assert_eq!(censor("darn", &banned), "****");
+        assert_eq!(censor("", &banned), "");
+    }
+
+    #[test]
+    fn censor_hides_phrases() {
+        let banned = banned(&["other word"]);
+
+        assert_eq!(
+            censor("the other word here", &banned),
+            "the ***** **** here"
+        );
+        assert_eq!(censor("other,  WORD!", &banned), "*****,  ****!");
+        assert_eq!(censor("other words", &banned), "other words");
+        assert_eq!(censor("word other", &banned), "word other");
+    }
+
+    #[test]
+    fn censor_keeps_the_rest_of_the_text() {
+        let banned = banned(&["straße"]);
+        assert_eq!(
+            censor("Die STRASSE, die Straße.", &banned),
+            "Die STRASSE, die ******."
+        );
+    }
+
+    #[test]
+    fn players_can_send_five_messages_at_a_time() {
+        let mut chat = Chat::default();
+
+        for _ in 0..RATE_MESSAGES {
+            assert!(chat.post(1, "a".to_string(), "hi").is_ok());
+        }
+
+        assert!(chat.post(1, "a".to_string(), "hi").is_err());
+        assert!(chat.post(2, "b".to_string(), "hi").is_ok());
+        assert_eq!(chat.messages.len(), RATE_MESSAGES + 1);
+    }
+
+    #[test]
+    fn older_messages_leave_the_window() {
+        let mut chat = Chat::default();
+        chat.sent
+            .insert(1, vec![now() - RATE_WINDOW; RATE_MESSAGES]);
+        assert!(chat.post(1, "a".to_string(), "hi").is_ok());
+
+        chat.sent.insert(1, vec![now(); RATE_MESSAGES]);
+        assert!(chat.post(1, "a".to_string(), "hi").is_err());
+    }
+
+    #[test]
+    fn empty_long_and_muted_messages_are_refused() {
+        let mut chat = Chat::default();
+
+        assert!(chat.post(1, "a".to_string(), "   ").is_err());
+        assert!(chat
+            .post(1, "a".to_string(), &"a".repeat(MAX_LENGTH + 1))
+            .is_err());
+        assert!(chat
+            .post(1, "a".to_string(), &"a".repeat(MAX_LENGTH))
+            .is_ok());
+
+        chat.mute(1, true);
+        assert!(chat.post(1, "a".to_string(), "hi").is_err());
+        assert!(chat.react(1, "a".to_string(), Reaction::Wow).is_err());
+
+        chat.mute(1, false);
+        assert!(chat.post(1, "a".to_string(), "hi").is_ok());
+    }
+
+    #[test]
+    fn reactions_have_a_cooldown() {
+        let mut chat = Chat::default();
+
+        assert!(chat.react(1, "a".to_string(), Reaction::Nice).is_ok());
+        assert!(chat.react(1, "a".to_string(), Reaction::Wow).is_err());
+        assert!(chat.react(2, "b".to_string(), Reaction::Wow).is_ok());
+
+        chat.reacted.insert(1, now() - REACTION_COOLDOWN);
+        assert!(chat.react(1, "a".to_string(), Reaction::Wow).is_ok());
+        assert!(chat.messages.is_empty());
+    }
+}
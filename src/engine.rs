@@ -0,0 +1,878 @@
+use crate::card::{Card, CardColor};
+use crate::event::{Event, EventKind};
+use crate::room::{Room, RuleSet};
+use crate::rules::{check_move, do_draw, do_pass, do_penalty, do_play, penalty_cards};
+use crate::script;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+pub type PlayerId = u32;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum Action {
+    Play {
+        card_id: u8,
+        color: Option<CardColor>,
+    },
+    Draw,
+    Pass,
+    Penalty,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    AlreadyStarted,
+    NotEnoughPlayers,
+    TooManyPlayers(usize),
+    UnevenTeams,
+    NotStarted,
+    RoundOver,
+    NotInRoom,
+    NotYourTurn,
+    NotInHand,
+    InvalidCard(String),
+    MissingColor,
+    WrongSide,
+    Vetoed,
+    PenaltyPending,
+    AlreadyDrew,
+    MustDraw,
+    NoPenalty,
+    DeckEmpty,
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::AlreadyStarted => f.write_str("Game already started"),
+            RuleError::NotEnoughPlayers => f.write_str("Not enough players"),
+            RuleError::TooManyPlayers(max) => {
+                write!(f, "The deck can only be dealt to {} players", max)
+            }
+            RuleError::UnevenTeams => f.write_str("Each team needs two players"),
+            RuleError::NotStarted => f.write_str("The game has not started"),
+            RuleError::RoundOver => f.write_str("The round is over"),
+            RuleError::NotInRoom => f.write_str("Player not in room"),
+            RuleError::NotYourTurn => f.write_str("Not your turn"),
+            RuleError::NotInHand => f.write_str("You don't have that card"),
+            RuleError::InvalidCard(message) => f.write_str(message),
+            RuleError::MissingColor => f.write_str("You must choose a color"),
+            RuleError::WrongSide => f.write_str("That color is on the other side"),
+            RuleError::Vetoed => f.write_str("The room's script doesn't allow that"),
+            RuleError::PenaltyPending => f.write_str("You can only chain or pass"),
+            RuleError::AlreadyDrew => f.write_str("You already drew a card"),
+            RuleError::MustDraw => f.write_str("You must draw one card"),
+            RuleError::NoPenalty => f.write_str("There is no penalty"),
+            RuleError::DeckEmpty => f.write_str("There are no cards left to draw"),
+        }
+    }
+}
+
+// What a client needs to grey out the hand: the cards that can be played
+// right now, which of them need a color and whether draw, pass or penalty
+// are allowed.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct LegalMoves {
+    pub playable: Vec<u8>,
+    pub needs_color: Vec<u8>,
+    pub colors: Vec<CardColor>,
+    pub draw: bool,
+    pub pass: bool,
+    pub penalty: bool,
+}
+
+pub struct Engine;
+
+impl Engine {
+    pub fn deal(
+        room: &mut Room,
+        player_id: PlayerId,
+        cards: &[Card],
+    ) -> Result<Vec<Event>, RuleError> {
+        if room.active {
+            return Err(RuleError::AlreadyStarted);
+        }
+
+        if room.players.len() < 2 {
+            return Err(RuleError::NotEnoughPlayers);
+        }
+
+        if room.players.len() > room.max_players(cards) {
+            return Err(RuleError::TooManyPlayers(room.max_players(cards)));
+        }
+
+        if room.teams.is_some() && !room.teams_ready() {
+            return Err(RuleError::UnevenTeams);
+        }
+
+        let deck = room.deal(cards);
+        Ok(vec![Event::new(player_id, EventKind::Deal { deck })])
+    }
+
+    pub fn validate(room: &Room, player_id: PlayerId, action: &Action) -> Result<(), RuleError> {
+        if !room.active {
+            return Err(RuleError::NotStarted);
+        }
+
+        if room.is_over() {
+            return Err(RuleError::RoundOver);
+        }
+
+        let player = room
+            .players
+            .iter()
+            .find(|player| player.id == player_id)
+            .ok_or(RuleError::NotInRoom)?;
+
+        if room.current().id != player_id {
+            return Err(RuleError::NotYourTurn);
+        }
+
+        match action {
+            Action::Play { card_id, color } => {
+                let (_, card) = player
+                    .hand
+                    .iter()
+                    .find(|(id, _)| id == card_id)
+                    .ok_or(RuleError::NotInHand)?;
+
+                if let Some(error) = check_move(room, card) {
+                    return Err(RuleError::InvalidCard(error));
+                }
+
+                if card.is_color_card() && color.is_none() {
+                    return Err(RuleError::MissingColor);
+                }
+
+                if card.is_color_card() && !color.iter().all(|c| room.colors().contains(c)) {
+                    return Err(RuleError::WrongSide);
+                }
+
+                if !script::allows(room, player_id, card) {
+                    return Err(RuleError::Vetoed);
+                }
+            }
+            Action::Draw if room.state.is_some() => return Err(RuleError::PenaltyPending),
+            Action::Draw if player.drawed => return Err(RuleError::AlreadyDrew),
+            Action::Draw if room.cards_left() == 0 => return Err(RuleError::DeckEmpty),
+            Action::Pass if room.state.is_some() => return Err(RuleError::PenaltyPending),
+            Action::Pass if !player.drawed && room.cards_left() > 0 => {
+                return Err(RuleError::MustDraw)
+            }
+            Action::Penalty if room.state.is_none() => return Err(RuleError::NoPenalty),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn legal_actions(room: &Room, player_id: PlayerId) -> Vec<Action> {
+        let player = match room.players.iter().find(|player| player.id == player_id) {
+            Some(player) => player,
+            None => return vec![],
+        };
+
+        let mut candidates = vec![];
+
+        for (card_id, card) in player.hand.iter() {
+            if card.is_color_card() {
+                candidates.extend(room.colors().iter().map(|color| Action::Play {
+                    card_id: *card_id,
+                    color: Some(color.clone()),
+                }));
+            } else {
+                candidates.push(Action::Play {
+                    card_id: *card_id,
+                    color: None,
+                });
+            }
+        }
+
+        candidates.extend(vec![Action::Draw, Action::Pass, Action::Penalty]);
+        candidates
+            .into_iter()
+            .filter(|action| Engine::validate(room, player_id, action).is_ok())
+            .collect()
+    }
+
+    pub fn legal_moves(room: &Room, player_id: PlayerId) -> LegalMoves {
+        let mut moves = LegalMoves::default();
+
+        for action in Engine::legal_actions(room, player_id) {
+            match action {
+                Action::Play { card_id, color } => {
+                    if !moves.playable.contains(&card_id) {
+                        moves.playable.push(card_id);
+                    }
+                    if color.is_some() && !moves.needs_color.contains(&card_id) {
+                        moves.needs_color.push(card_id);
+                    }
+                }
+                Action::Draw => moves.draw = true,
+                Action::Pass => moves.pass = true,
+                Action::Penalty => moves.penalty = true,
+            }
+        }
+
+        if !moves.needs_color.is_empty() {
+            moves.colors = room.colors().to_vec();
+        }
+
+        moves
+    }
+
+    pub fn apply(
+        room: &mut Room,
+        player_id: PlayerId,
+        action: Action,
+    ) -> Result<Vec<Event>, RuleError> {
+        Engine::validate(room, player_id, &action)?;
+
+        let kinds = match action {
+            Action::Play { card_id, color } => {
+                let card = room
+                    .player(player_id)
+                    .hand
+                    .iter()
+                    .find(|(id, _)| *id == card_id)
+                    .cloned()
+                    .unwrap();
+                do_play(room, player_id, card, color)
+            }
+            // No Mercy keeps drawing until something can be played, or the
+            // turn has moved on: a knockout or a script's skip ends it.
+            Action::Draw => {
+                let mut kinds = vec![];
+
+                while let Some(card) = room.draw() {
+                    do_draw(room, player_id, card.clone());
+                    let playable = check_move(room, &card.1).is_none();
+                    kinds.push(EventKind::Draw { card });
+
+                    if room.rules != RuleSet::NoMercy || playable || room.current().id != player_id
+                    {
+                        break;
+                    }
+                }
+
+                kinds
+            }
+            Action::Pass => {
+                do_pass(room, player_id);
+                vec![EventKind::Pass]
+            }
+            Action::Penalty => {
+                let cards = penalty_cards(room);
+                do_penalty(room, player_id, cards.clone());
+                vec![EventKind::Penalty { cards }]
+            }
+        };
+
+        room.update_player();
+
+        let drawn = room
+            .drawn
+            .drain(..)
+            .map(|(player, cards)| EventKind::Drawn { player, cards });
+
+        Ok(kinds
+            .into_iter()
+            .chain(drawn)
+            .map(|kind| Event::new(player_id, kind))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::card::{all, flip, GameState};
+    use crate::room::MERCY;
+    use crate::rules::penalty_amount;
+    use crate::script::tests::scripted;
+    use crate::strategy::DARK_COLORS;
+    use crate::user::User;
+
+    pub(crate) fn red(number: u8) -> Card {
+        Card::Number(number, CardColor::Red)
+    }
+
+    // Players 1, 2, ... in seats 0, 1, ..., seat 0 to play, on a red 5.
+    pub(crate) fn table(hands: Vec<Vec<Card>>) -> Room {
+        let mut room = Room::new();
+
+        for (seat, hand) in hands.into_iter().enumerate() {
+            let mut user = User::new(format!("p{}", seat + 1), seat as u32 + 1);
+            user.hand = hand
+                .into_iter()
+                .enumerate()
+                .map(|(i, card)| ((seat * 20 + i) as u8, card))
+                .collect();
+            room.players.push(user);
+        }
+
+        room.deck = (0..20)
+            .map(|i| (100 + i, Card::Number(i % 10, CardColor::Blue)))
+            .collect();
+        room.board = vec![(200, red(5))];
+        room.active = true;
+        room.update_player();
+        room
+    }
+
+    pub(crate) fn play(
+        room: &mut Room,
+        player_id: u32,
+        card: &Card,
+        color: Option<CardColor>,
+    ) -> Vec<Event> {
+        let card_id = room
+            .players
+            .iter()
+            .find(|player| player.id == player_id)
+            .and_then(|player| player.hand.iter().find(|(_, c)| c == card))
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        Engine::apply(room, player_id, Action::Play { card_id, color }).unwrap()
+    }
+
+    pub(crate) fn current(room: &Room) -> u32 {
+        room.current().id
+    }
+
+    #[test]
+    fn number_cards_pass_the_turn_along() {
+        let mut room = table(vec![vec![red(1), red(2)]; 3]);
+        play(&mut room, 1, &red(1), None);
+        assert_eq!(current(&room), 3);
+        play(&mut room, 3, &red(1), None);
+        assert_eq!(current(&room), 2);
+    }
+
+    #[test]
+    fn stop_skips_the_next_player_right_away() {
+        let mut room = table(vec![
+            vec![Card::Stop(CardColor::Red), red(1)],
+            vec![red(2)],
+            vec![red(3)],
+        ]);
+        play(&mut room, 1, &Card::Stop(CardColor::Red), None);
+
+        assert_eq!(current(&room), 2);
+        assert_eq!(room.state, None);
+        assert_eq!(penalty_amount(&room), None);
+        assert!(!Engine::legal_actions(&room, 2).contains(&Action::Penalty));
+    }
+
+    #[test]
+    fn stop_with_two_players_plays_again() {
+        let mut room = table(vec![vec![Card::Stop(CardColor::Red), red(1)], vec![red(2)]]);
+        play(&mut room, 1, &Card::Stop(CardColor::Red), None);
+
+        assert_eq!(current(&room), 1);
+        assert_eq!(room.state, None);
+    }
+
+    #[test]
+    fn stop_plays_on_a_stop_without_chaining() {
+        let stop = |color| Card::Stop(color);
+        let mut room = table(vec![
+            vec![stop(CardColor::Red), red(1)],
+            vec![stop(CardColor::Blue), red(2)],
+            vec![stop(CardColor::Green), red(3)],
+        ]);
+        play(&mut room, 1, &stop(CardColor::Red), None);
+        play(&mut room, 2, &stop(CardColor::Blue), None);
+
+        assert_eq!(current(&room), 3);
+        assert_eq!(room.chain_count, 0);
+        assert_eq!(room.state, None);
+    }
+
+    #[test]
+    fn stop_passes_over_empty_hands_and_knocked_out_players() {
+        let mut room = table(vec![
+            vec![Card::Stop(CardColor::Red), red(1)],
+            vec![red(2)],
+            vec![red(3)],
+            vec![],
+            vec![red(4)],
+        ]);
+        room.eliminated.push(3);
+        play(&mut room, 1, &Card::Stop(CardColor::Red), None);
+
+        // Player 5 is skipped. Player 4's hand is empty and player 3 is out,
+        // so player 2 plays.
+        assert_eq!(current(&room), 2);
+    }
+
+    #[test]
+    fn stop_as_last_card_still_skips() {
+        let mut room = table(vec![
+            vec![Card::Stop(CardColor::Red)],
+            vec![red(2)],
+            vec![red(3)],
+        ]);
+        play(&mut room, 1, &Card::Stop(CardColor::Red), None);
+        assert_eq!(current(&room), 2);
+    }
+
+    #[test]
+    fn reverse_turns_the_direction_around() {
+        let reverse = Card::Reverse(CardColor::Red);
+        let mut room = table(vec![
+            vec![reverse.clone(), red(1)],
+            vec![red(2), red(3)],
+            vec![red(4)],
+        ]);
+        play(&mut room, 1, &reverse, None);
+
+        assert!(room.direction);
+        assert_eq!(current(&room), 2);
+        play(&mut room, 2, &red(2), None);
+        assert_eq!(current(&room), 3);
+    }
+
+    #[test]
+    fn reverse_with_two_players_is_a_skip() {
+        let reverse = Card::Reverse(CardColor::Red);
+        let mut room = table(vec![vec![reverse.clone(), red(1)], vec![red(2)]]);
+        play(&mut room, 1, &reverse, None);
+
+        assert_eq!(current(&room), 1);
+        assert!(room.direction);
+    }
+
+    #[test]
+    fn reverse_with_two_players_left_is_a_skip() {
+        let reverse = Card::Reverse(CardColor::Red);
+        let mut room = table(vec![vec![reverse.clone(), red(1)], vec![], vec![red(2)]]);
+        play(&mut room, 1, &reverse, None);
+        assert_eq!(current(&room), 1);
+
+        let mut room = table(vec![
+            vec![reverse.clone(), red(1)],
+            vec![red(3)],
+            vec![red(2)],
+        ]);
+        room.rules = RuleSet::NoMercy;
+        room.eliminated.push(2);
+        play(&mut room, 1, &reverse, None);
+        assert_eq!(current(&room), 1);
+    }
+
+    #[test]
+    fn reverse_with_four_players_goes_back() {
+        let reverse = Card::Reverse(CardColor::Red);
+        let mut room = table(vec![
+            vec![red(1), red(2)],
+            vec![reverse.clone(), red(3)],
+            vec![red(4)],
+            vec![red(5)],
+        ]);
+        play(&mut room, 1, &red(1), None);
+        assert_eq!(current(&room), 4);
+
+        let mut room = table(vec![
+            vec![reverse.clone(), red(1)],
+            vec![red(2)],
+            vec![red(3)],
+            vec![red(4)],
+        ]);
+        play(&mut room, 1, &reverse, None);
+        assert_eq!(current(&room), 2);
+    }
+
+    #[test]
+    fn reverse_plus_4_with_two_players_hands_the_penalty_on() {
+        let mut room = table(vec![vec![Card::ReversePlus4, red(1)], vec![red(2)]]);
+        room.rules = RuleSet::NoMercy;
+        play(&mut room, 1, &Card::ReversePlus4, Some(CardColor::Red));
+
+        assert_eq!(current(&room), 2);
+        assert_eq!(room.state, Some(GameState::Stack));
+        assert_eq!(penalty_amount(&room), Some(4));
+    }
+
+    #[test]
+    fn skip_everyone_plays_again_unless_it_was_the_last_card() {
+        let skip = Card::SkipEveryone(CardColor::Red);
+        let mut room = table(vec![vec![skip.clone(), red(1)], vec![red(2)], vec![red(3)]]);
+        play(&mut room, 1, &skip, None);
+        assert_eq!(current(&room), 1);
+
+        let mut room = table(vec![vec![skip.clone()], vec![red(2)], vec![red(3)]]);
+        play(&mut room, 1, &skip, None);
+        assert_eq!(current(&room), 3);
+    }
+
+    #[test]
+    fn draw_cards_leave_the_next_player_the_penalty() {
+        let plus2 = Card::Plus2(CardColor::Red);
+        let mut room = table(vec![
+            vec![plus2.clone(), red(1)],
+            vec![red(2)],
+            vec![red(3)],
+        ]);
+        play(&mut room, 1, &plus2, None);
+
+        assert_eq!(current(&room), 3);
+        assert_eq!(penalty_amount(&room), Some(2));
+        Engine::apply(&mut room, 3, Action::Penalty).unwrap();
+        assert_eq!(room.players[2].hand.len(), 3);
+        assert_eq!(current(&room), 2);
+    }
+
+    #[test]
+    fn a_stop_left_pending_by_an_old_room_can_be_passed() {
+        let mut room = table(vec![vec![red(1)], vec![red(2)], vec![red(3)]]);
+        room.state = Some(GameState::Stop);
+
+        assert_eq!(penalty_amount(&room), Some(0));
+        Engine::apply(&mut room, 1, Action::Penalty).unwrap();
+        assert_eq!(room.state, None);
+        assert_eq!(room.players[0].hand.len(), 1);
+        assert_eq!(current(&room), 3);
+    }
+
+    fn seated(players: u32) -> Room {
+        let mut room = Room::new();
+
+        for id in 1..=players {
+            room.seat(User::new(format!("p{}", id), id));
+        }
+
+        room
+    }
+
+    #[test]
+    fn deal_needs_two_to_seven_classic_players() {
+        assert_eq!(
+            Engine::deal(&mut seated(1), 1, &all()).unwrap_err(),
+            RuleError::NotEnoughPlayers
+        );
+        assert_eq!(
+            Engine::deal(&mut seated(8), 1, &all()).unwrap_err(),
+            RuleError::TooManyPlayers(7)
+        );
+
+        let mut room = seated(7);
+        assert!(Engine::deal(&mut room, 1, &all()).is_ok());
+        assert!(room.active);
+        assert_eq!(
+            Engine::deal(&mut room, 1, &all()).unwrap_err(),
+            RuleError::AlreadyStarted
+        );
+    }
+
+    #[test]
+    fn bigger_decks_deal_to_more_players() {
+        let mut room = seated(8);
+        room.rules = RuleSet::NoMercy;
+        assert!(Engine::deal(&mut room, 1, &all()).is_ok());
+    }
+
+    #[test]
+    fn actions_are_checked_before_they_are_applied() {
+        let mut room = table(vec![vec![red(1), Card::Plus4], vec![red(2)], vec![red(3)]]);
+        let plus4 = Action::Play {
+            card_id: 1,
+            color: None,
+        };
+
+        assert_eq!(
+            Engine::apply(&mut room, 9, Action::Draw).unwrap_err(),
+            RuleError::NotInRoom
+        );
+        assert_eq!(
+            Engine::apply(&mut room, 2, Action::Draw).unwrap_err(),
+            RuleError::NotYourTurn
+        );
+        assert_eq!(
+            Engine::validate(
+                &room,
+                1,
+                &Action::Play {
+                    card_id: 20,
+                    color: None
+                }
+            ),
+            Err(RuleError::NotInHand)
+        );
+        assert_eq!(
+            Engine::validate(&room, 1, &plus4),
+            Err(RuleError::MissingColor)
+        );
+        assert_eq!(
+            Engine::validate(&room, 1, &Action::Pass),
+            Err(RuleError::MustDraw)
+        );
+        assert_eq!(
+            Engine::validate(&room, 1, &Action::Penalty),
+            Err(RuleError::NoPenalty)
+        );
+
+        Engine::apply(&mut room, 1, Action::Draw).unwrap();
+        assert_eq!(
+            Engine::validate(&room, 1, &Action::Draw),
+            Err(RuleError::AlreadyDrew)
+        );
+        assert!(Engine::validate(&room, 1, &Action::Pass).is_ok());
+    }
+
+    #[test]
+    fn a_pending_penalty_can_only_be_chained_or_taken() {
+        let plus2 = Card::Plus2(CardColor::Red);
+        let mut room = table(vec![
+            vec![plus2.clone(), red(1)],
+            vec![red(2)],
+            vec![plus2.clone(), red(3)],
+        ]);
+        play(&mut room, 1, &plus2, None);
+
+        assert_eq!(
+            Engine::validate(&room, 3, &Action::Draw),
+            Err(RuleError::PenaltyPending)
+        );
+        assert_eq!(
+            Engine::validate(&room, 3, &Action::Pass),
+            Err(RuleError::PenaltyPending)
+        );
+        assert_eq!(
+            Engine::legal_moves(&room, 3),
+            LegalMoves {
+                playable: vec![40],
+                penalty: true,
+                ..LegalMoves::default()
+            }
+        );
+    }
+
+    #[test]
+    fn legal_moves_sum_up_the_legal_actions() {
+        let room = table(vec![
+            vec![red(1), Card::Plus4, Card::Number(1, CardColor::Green)],
+            vec![red(2)],
+        ]);
+        let moves = Engine::legal_moves(&room, 1);
+
+        assert_eq!(moves.playable, vec![0, 1]);
+        assert_eq!(moves.needs_color, vec![1]);
+        assert_eq!(moves.colors, room.colors().to_vec());
+        assert!(moves.draw);
+        assert!(!moves.pass);
+        assert!(!moves.penalty);
+        assert_eq!(Engine::legal_moves(&room, 2), LegalMoves::default());
+    }
+
+    #[test]
+    fn nothing_can_be_done_once_the_round_is_over() {
+        let mut room = table(vec![vec![red(1)], vec![red(2)]]);
+        play(&mut room, 1, &red(1), None);
+
+        assert!(room.is_over());
+        assert_eq!(
+            Engine::validate(&room, 2, &Action::Draw),
+            Err(RuleError::RoundOver)
+        );
+        assert!(Engine::legal_actions(&room, 2).is_empty());
+    }
+
+    // Moves a card from the draw pile to the hand of whoever is to play.
+    fn hand_over(room: &mut Room, card: &Card) -> u8 {
+        let index = room.deck.iter().position(|(_, c)| c == card).unwrap();
+        let card = room.deck.remove(index);
+        let player_id = current(room);
+        room.player(player_id).hand.push(card.clone());
+        card.0
+    }
+
+    // A dealt Flip round where the first player holds a light Flip card of
+    // the top card's color.
+    fn flip_table() -> (Room, u8) {
+        let mut room = seated(3);
+        room.rules = RuleSet::Flip;
+        room.seed = 3;
+        Engine::deal(&mut room, 1, &all()).unwrap();
+        room.state = None;
+
+        let flip_card = Card::Flip(room.top().1.get_color().unwrap());
+        let card_id = hand_over(&mut room, &flip_card);
+        (room, card_id)
+    }
+
+    // Whether every card shows the given side of the Flip deck.
+    fn showing(room: &Room, dark: bool) -> bool {
+        let faces = flip();
+        let face = |id: u8| {
+            let (light, dark_face) = &faces[id as usize];
+            if dark {
+                dark_face.clone()
+            } else {
+                light.clone()
+            }
+        };
+        let shows = |cards: &[(u8, Card)]| cards.iter().all(|(id, card)| *card == face(*id));
+
+        shows(&room.deck)
+            && shows(&room.board)
+            && room.players.iter().all(|player| shows(&player.hand))
+    }
+
+    #[test]
+    fn a_flip_card_turns_the_whole_table_over() {
+        let (mut room, card_id) = flip_table();
+        assert!(!room.dark && showing(&room, false));
+
+        let player_id = current(&room);
+        let play = Action::Play {
+            card_id,
+            color: None,
+        };
+        Engine::apply(&mut room, player_id, play).unwrap();
+
+        assert!(room.dark);
+        assert!(showing(&room, true));
+        assert_eq!(room.top(), (card_id, flip()[card_id as usize].1.clone()));
+    }
+
+    #[test]
+    fn dark_cards_are_checked_against_the_dark_top_card() {
+        let (mut room, card_id) = flip_table();
+        let player_id = current(&room);
+        let play = Action::Play {
+            card_id,
+            color: None,
+        };
+        Engine::apply(&mut room, player_id, play).unwrap();
+
+        let player_id = current(&room);
+        let color = room.top().1.get_color().unwrap();
+        let other = DARK_COLORS.iter().find(|c| **c != color).unwrap().clone();
+        room.player(player_id).hand = vec![
+            (200, Card::Number(1, color.clone())),
+            (201, Card::SkipEveryone(other.clone())),
+            (202, Card::Number(2, CardColor::Red)),
+        ];
+        let card = |card_id| Action::Play {
+            card_id,
+            color: None,
+        };
+
+        assert!(Engine::validate(&room, player_id, &card(200)).is_ok());
+        assert!(matches!(
+            Engine::validate(&room, player_id, &card(201)),
+            Err(RuleError::InvalidCard(_))
+        ));
+        assert!(matches!(
+            Engine::validate(&room, player_id, &card(202)),
+            Err(RuleError::InvalidCard(_))
+        ));
+    }
+
+    #[test]
+    fn wild_colors_must_be_from_the_side_showing() {
+        let (mut room, card_id) = flip_table();
+        let player_id = current(&room);
+        let wild_id = hand_over(&mut room, &Card::ChangeColor);
+        let wild = |color: CardColor| Action::Play {
+            card_id: wild_id,
+            color: Some(color),
+        };
+
+        assert_eq!(
+            Engine::validate(&room, player_id, &wild(CardColor::Pink)),
+            Err(RuleError::WrongSide)
+        );
+        assert!(Engine::validate(&room, player_id, &wild(CardColor::Red)).is_ok());
+
+        let play = Action::Play {
+            card_id,
+            color: None,
+        };
+        Engine::apply(&mut room, player_id, play).unwrap();
+        let player_id = current(&room);
+        let wild_id = hand_over(&mut room, &Card::ChangeColor);
+        let wild = |color: CardColor| Action::Play {
+            card_id: wild_id,
+            color: Some(color),
+        };
+
+        assert_eq!(
+            Engine::validate(&room, player_id, &wild(CardColor::Red)),
+            Err(RuleError::WrongSide)
+        );
+        assert!(Engine::validate(&room, player_id, &wild(CardColor::Teal)).is_ok());
+    }
+
+    fn draws(events: &[Event]) -> usize {
+        events
+            .iter()
+            .filter(|event| matches!(event.kind, EventKind::Draw { .. }))
+            .count()
+    }
+
+    #[test]
+    fn no_mercy_draws_until_a_card_fits() {
+        let green = Card::Number(3, CardColor::Green);
+        let mut room = table(vec![vec![green.clone()], vec![red(1)], vec![red(2)]]);
+        room.rules = RuleSet::NoMercy;
+
+        // Blue 0 to 4 don't fit on the red 5, the blue 5 does.
+        let events = Engine::apply(&mut room, 1, Action::Draw).unwrap();
+        assert_eq!(draws(&events), 6);
+        assert_eq!(room.player(1).hand.len(), 7);
+        assert_eq!(current(&room), 1);
+        assert!(Engine::validate(&room, 1, &Action::Pass).is_ok());
+    }
+
+    #[test]
+    fn the_mercy_rule_knocks_out_a_full_hand() {
+        let green = Card::Number(3, CardColor::Green);
+        let mut room = table(vec![vec![green; MERCY - 1], vec![red(1)], vec![red(2)]]);
+        room.rules = RuleSet::NoMercy;
+        let deck = room.deck.len();
+
+        let events = Engine::apply(&mut room, 1, Action::Draw).unwrap();
+        assert_eq!(draws(&events), 1);
+        assert_eq!(room.eliminated, vec![1]);
+        assert!(room.player(1).hand.is_empty());
+        assert_eq!(room.deck.len(), deck + MERCY - 1);
+        assert_eq!(current(&room), 3);
+        assert!(!room.is_over());
+    }
+
+    #[test]
+    fn a_skip_on_draw_ends_the_drawing() {
+        let green = Card::Number(3, CardColor::Green);
+        let mut room = scripted(
+            "skip_on_draw",
+            vec![vec![green], vec![red(1)], vec![red(2)]],
+        );
+        room.rules = RuleSet::NoMercy;
+
+        let events = Engine::apply(&mut room, 1, Action::Draw).unwrap();
+        assert_eq!(draws(&events), 1);
+        assert_eq!(room.player(1).hand.len(), 2);
+        assert_eq!(current(&room), 3);
+    }
+
+    #[test]
+    fn discard_all_takes_the_cards_of_its_color_along() {
+        let discard = Card::DiscardAll(CardColor::Red);
+        let green = Card::Number(3, CardColor::Green);
+        let mut room = table(vec![
+            vec![discard.clone(), red(1), green.clone(), red(2)],
+            vec![red(3)],
+        ]);
+        room.rules = RuleSet::NoMercy;
+        play(&mut room, 1, &discard, None);
+
+        assert_eq!(room.player(1).hand, vec![(2, green)]);
+        assert_eq!(room.top().1, discard);
+        let under: Vec<Card> = room.board.iter().map(|(_, card)| card.clone()).collect();
+        assert_eq!(under, vec![red(5), red(1), red(2), discard]);
+        assert_eq!(current(&room), 2);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::card::{all, Card, CardColor};
+use crate::house::CardDef;
+use crate::room::{Room, RuleSet, Teams};
+use crate::rules::{do_draw, do_pass, do_penalty, do_turn, effects};
+use crate::strategy::BotKind;
+use crate::user::User;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum EventKind {
+    Seed {
+        seed: u64,
+    },
+    Rules {
+        rules: RuleSet,
+    },
+    Join {
+        name: String,
+        #[serde(default)]
+        bot: Option<BotKind>,
+    },
+    Leave,
+    Teams {
+        teams: Teams,
+    },
+    PickTeam {
+        team: u8,
+    },
+    House {
+        cards: Vec<CardDef>,
+    },
+    Script {
+        name: String,
+    },
+    Import {
+        room: Box<Room>,
+    },
+    Deal {
+        deck: Vec<(u8, Card)>,
+    },
+    Play {
+        card: (u8, Card),
+    },
+    ChooseColor {
+        color: CardColor,
+    },
+    Draw {
+        card: (u8, Card),
+    },
+    Pass,
+    Penalty {
+        cards: Vec<(u8, Card)>,
+    },
+    // Cards a house card or the room's script made `player` take. Replaying
+    // the card that caused it draws them again, so this is only a record.
+    Drawn {
+        player: u32,
+        cards: Vec<(u8, Card)>,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Event {
+    pub at: u64,
+    pub player_id: u32,
+    pub kind: EventKind,
+}
+
+impl Event {
+    pub fn new(player_id: u32, kind: EventKind) -> Event {
+        Event {
+            at: now(),
+            player_id,
+            kind,
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Shuffles are redone from the room seed, so the recorded cards are not
+// needed to rebuild the deck and only serve to read the log. Logs are replayed
+// with today's rules: rounds dealt before the turn order and opening card
+// followed the official rules don't replay to the room they were.
+pub fn replay(events: &[Event]) -> Room {
+    replay_with(events, |_, _| {})
+}
+
+// Replays the log like `replay`, showing `visit` the room as it stood just
+// before each event.
+pub fn replay_with(events: &[Event], mut visit: impl FnMut(&Room, &Event)) -> Room {
+    let mut room = Room::new();
+
+    for (index, event) in events.iter().enumerate() {
+        visit(&room, event);
+        apply(&mut room, event, events.get(index + 1));
+
+        if room.active {
+            room.update_player();
+            room.end_round();
+        }
+    }
+
+    room
+}
+
+// A wild's color is logged as its own event after the play, but the live game
+// sets it before the card takes effect, so it is looked up ahead here.
+fn apply(room: &mut Room, event: &Event, next: Option<&Event>) {
+    let player_id = event.player_id;
+
+    match &event.kind {
+        EventKind::Seed { seed } => room.seed = *seed,
+        EventKind::Rules { rules } => room.rules = rules.clone(),
+        EventKind::Join { name, bot } => {
+            let mut user = User::new(name.clone(), player_id);
+            user.bot = bot.clone();
+            room.seat(user);
+        }
+        EventKind::Leave => room.players.retain(|player| player.id != player_id),
+        EventKind::Teams { teams } => room.teams = Some(teams.clone()),
+        EventKind::PickTeam { team } => room.player(player_id).team = Some(*team),
+        EventKind::House { cards } => room.house = cards.clone(),
+        EventKind::Script { name } => room.script = Some(name.clone()),
+        EventKind::Import { room: snapshot } => *room = (**snapshot).clone(),
+        EventKind::Deal { .. } => {
+            room.deal(&all());
+        }
+        EventKind::Play { card } => {
+            let color = match next.map(|next| &next.kind) {
+                Some(EventKind::ChooseColor { color }) => Some(color.clone()),
+                _ => None,
+            };
+            effects(room, card.clone(), color);
+            do_turn(room, player_id, card.clone());
+        }
+        EventKind::ChooseColor { color } => room.color = color.clone(),
+        EventKind::Draw { .. } => {
+            if let Some(card) = room.draw() {
+                do_draw(room, player_id, card);
+            }
+        }
+        EventKind::Pass => do_pass(room, player_id),
+        EventKind::Penalty { cards } => {
+            let cards = cards.iter().filter_map(|_| room.draw()).collect();
+            do_penalty(room, player_id, cards);
+        }
+        EventKind::Drawn { .. } => {}
+    }
+
+    room.drawn.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    // Plays a whole round through the engine, logging events as the handlers
+    // do, and picks moves with a small generator so runs are repeatable.
+    fn play(rules: RuleSet, seed: u64) -> (Room, Vec<Event>) {
+        let mut room = Room::new();
+        room.seed = seed;
+        room.rules = rules.clone();
+        let mut events = vec![
+            Event::new(0, EventKind::Seed { seed }),
+            Event::new(0, EventKind::Rules { rules }),
+        ];
+
+        for id in 1..=3 {
+            room.seat(User::new(format!("p{}", id), id));
+            let name = format!("p{}", id);
+            events.push(Event::new(id, EventKind::Join { name, bot: None }));
+        }
+
+        events.extend(Engine::deal(&mut room, 1, &all()).unwrap());
+        let mut pick = seed;
+
+        for _ in 0..3000 {
+            if room.is_over() {
+                break;
+            }
+
+            let player_id = room.current().id;
+            let actions = Engine::legal_actions(&room, player_id);
+            pick = pick
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let action = actions[(pick >> 33) as usize % actions.len()].clone();
+
+            events.extend(Engine::apply(&mut room, player_id, action).unwrap());
+            room.end_round();
+        }
+
+        (room, events)
+    }
+
+    fn json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn replay_rebuilds_the_live_room() {
+        for rules in [RuleSet::Classic, RuleSet::Flip, RuleSet::NoMercy] {
+            for seed in 0..5 {
+                let (live, events) = play(rules.clone(), seed);
+                let replayed = replay(&events);
+
+                assert_eq!(json(&replayed), json(&live), "{:?} {}", rules, seed);
+                assert_eq!(json(&replayed.players), json(&live.players));
+            }
+        }
+    }
+
+    #[test]
+    fn wild_colors_are_set_before_the_card_takes_effect() {
+        let mut room = Room::new();
+        room.seat(User::new("a".to_string(), 1));
+        room.seat(User::new("b".to_string(), 2));
+        room.deal(&all());
+        room.player(1).hand.push((200, Card::ChangeColor));
+        let snapshot = Box::new(room);
+
+        let play = Event::new(
+            1,
+            EventKind::Play {
+                card: (200, Card::ChangeColor),
+            },
+        );
+        let color = Event::new(
+            1,
+            EventKind::ChooseColor {
+                color: CardColor::Blue,
+            },
+        );
+        let events = [
+            Event::new(0, EventKind::Import { room: snapshot }),
+            play,
+            color,
+        ];
+
+        let mut replayed = replay(&events[..1]);
+        apply(&mut replayed, &events[1], events.get(2));
+        assert_eq!(replayed.color, CardColor::Blue);
+    }
+}
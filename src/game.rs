@@ -1,18 +1,21 @@
-use crate::card::{Card, CardColor, GameState};
-use crate::room::Room;
-use crate::uno::{room_and_player, Uno};
+use crate::bot;
+use crate::card::{Card, CardColor};
+use crate::engine::{Action, Engine, LegalMoves, RuleError};
+use crate::event::{Event, EventKind};
+use crate::room::RoomView;
+use crate::uno::{notify, room_and_player, Uno};
 use crate::user::User;
 use actix_session::Session;
 use actix_web::{web, HttpRequest, Responder};
-use rand::seq::SliceRandom;
-use rand::thread_rng;
 use serde::{Deserialize, Serialize};
-use std::sync::{Mutex, MutexGuard};
+use std::sync::Mutex;
 
 #[derive(Serialize)]
 struct Game {
-    pub room: Room,
+    pub room: RoomView,
     pub player: User,
+    pub partner: Option<User>,
+    pub moves: LegalMoves,
 }
 
 pub async fn play(
@@ -28,154 +31,108 @@ pub async fn play(
     if maybe_room.is_none() {
         return Err(actix_web::error::ErrorNotFound("Room does not exist"));
     }
-    
-    let room = maybe_room.unwrap();
-    let players_amount = room.players.len();
 
-    if players_amount < 2 {
-        return Err(actix_web::error::ErrorBadRequest("Not enough players"));
-    }
+    let room = maybe_room.unwrap();
 
     if !room.active {
-        room.deck = all
-            .iter()
-            .enumerate()
-            .map(|(i, c)| (i as u8, c.clone()))
-            .collect();
-        let mut rng = thread_rng();
-        room.deck.shuffle(&mut rng);
-
-        for player in room.players.iter_mut() {
-            let (hand, new_deck) = (room.deck[0..7].to_vec(), room.deck[8..].to_vec());
-            room.deck = new_deck;
-            player.hand = hand;
-        }
-
-        room.to_center();
-
-        while !room.top().1.is_normal() {
-            room.to_center();
-        }
-
-        room.active = true;
+        let events = Engine::deal(room, player_id, &all)
+            .map_err(|error| actix_web::error::ErrorBadRequest(error.to_string()))?;
+        context.save_room(room_id, events);
+        actix_web::rt::spawn(bot::run(data.clone(), room_id));
     }
 
+    let room = context.rooms.get_mut(&room_id).unwrap();
     room.update_player();
     let player = room.player(player_id).clone();
+    let partner = match &room.teams {
+        Some(teams) if teams.open_hands => room.partner(player_id).cloned(),
+        _ => None,
+    };
 
     Ok(web::Json(Game {
-        room: room.clone(),
+        room: room.view(player_id),
         player,
+        partner,
+        moves: Engine::legal_moves(room, player_id),
     }))
 }
 
-#[derive(Deserialize, Clone)]
-pub struct TurnReq {
-    pub card_id: u8,
-    pub color: Option<CardColor>,
-}
-
-#[derive(Serialize)]
-pub struct TurnRes {
-    pub room_id: u32,
-}
-
-pub async fn turn(
+pub async fn legal_moves(
     data: web::Data<Mutex<Uno>>,
     req: HttpRequest,
     session: Session,
-    form: web::Json<TurnReq>,
 ) -> impl Responder {
-    let mut context = data.lock().unwrap();
-    let all = context.cards.clone();
+    let context = data.lock().unwrap();
     let (room_id, player_id) = room_and_player(req, session);
-    let room = context.rooms.get_mut(&room_id).unwrap();
-
-    if room.current_player.id != player_id {
-        return Err(actix_web::error::ErrorUnauthorized("Not your turn"));
-    }
-
-    let card_1 = room.top();
-    let card_2 = all[form.card_id as usize].clone();
 
-    let choosen_color = if card_1.1.is_color_card() {
-        room.color.clone()
-    } else {
-        CardColor::Red
+    let room = match context.rooms.get(&room_id) {
+        Some(room) => room,
+        None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
     };
 
-    match card_1
-        .1
-        .matches(room.state.clone(), choosen_color, card_2.clone())
-    {
-        Some(error) => Err(actix_web::error::ErrorBadRequest(error)),
-        None => {
-            effects(room, (form.card_id, card_2.clone()), form.clone());
-            do_turn(room, player_id, (form.card_id, card_2));
-            notify(&mut context, room_id).await;
-            Ok("")
-        }
+    if !room.players.iter().any(|player| player.id == player_id) {
+        return Err(actix_web::error::ErrorUnauthorized("Player not in room"));
     }
-}
 
-async fn notify(context: &mut MutexGuard<'_, Uno>, room_id: u32) {
-    let subscribers = context.subscribers.get_mut(&room_id).unwrap();
-    for subscription in subscribers.iter_mut() {
-        let _ = subscription.session.text("update").await;
-    }
+    Ok(web::Json(Engine::legal_moves(room, player_id)))
 }
 
-pub fn effects(room: &mut Room, card_tuple: (u8, Card), form: TurnReq) {
-    if card_tuple.1.is_color_card() {
-        room.color = form.color.unwrap();
-    }
-
-    if card_tuple.1.can_chain() {
-        room.state = card_tuple.1.to_game_state();
-        room.chain_count += 1;
-    } else {
-        room.chain_count = 0;
-    }
+// Runs one action through the engine, tells every socket in the room about
+// it and lets the bots answer.
+async fn act(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    action: Action,
+) -> actix_web::Result<&'static str> {
+    let (room_id, player_id) = room_and_player(req, session);
+    let sockets = {
+        let mut context = data.lock().unwrap();
+        let room = match context.rooms.get_mut(&room_id) {
+            Some(room) => room,
+            None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+        };
+
+        let events = Engine::apply(room, player_id, action).map_err(|error| match error {
+            RuleError::NotYourTurn => actix_web::error::ErrorUnauthorized(error.to_string()),
+            _ => actix_web::error::ErrorBadRequest(error.to_string()),
+        })?;
+
+        context.save_room(room_id, events);
+        context.sockets(room_id)
+    };
 
-    if card_tuple.1.is_reverse() {
-        room.direction = !room.direction;
-    }
+    notify(sockets).await;
+    actix_web::rt::spawn(bot::run(data, room_id));
+    Ok("")
 }
 
-pub fn do_turn(room: &mut Room, player_id: u32, card_tuple: (u8, Card)) {
-    let player = room.player(player_id);
-    player.hand = player
-        .hand
-        .clone()
-        .into_iter()
-        .filter(|(id, _)| *id != card_tuple.0)
-        .collect();
-    player.drawed = false;
-    room.board.push(card_tuple);
-    room.next();
+#[derive(Deserialize, Clone)]
+pub struct TurnReq {
+    pub card_id: u8,
+    pub color: Option<CardColor>,
 }
 
-pub async fn draw(
+pub async fn turn(
     data: web::Data<Mutex<Uno>>,
     req: HttpRequest,
     session: Session,
+    form: web::Json<TurnReq>,
 ) -> impl Responder {
-    let mut context = data.lock().unwrap();
-    let (room_id, player_id) = room_and_player(req, session);
-    let room = context.rooms.get_mut(&room_id).unwrap();
-    let card = room.draw();
-    let current_player_id = room.current_player.id;
-    let player = room.player(player_id);
-
-    if current_player_id != player_id {
-        return Err(actix_web::error::ErrorUnauthorized("Not your turn"));
-    }
+    let action = Action::Play {
+        card_id: form.card_id,
+        color: form.color.clone(),
+    };
 
-    player.hand.push(card);
-    player.drawed = true;
-    notify(&mut context, room_id).await;
+    act(data, req, session, action).await
+}
 
-    Ok("")
+pub async fn draw(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+) -> impl Responder {
+    act(data, req, session, Action::Draw).await
 }
 
 pub async fn penalty(
@@ -183,56 +140,147 @@ pub async fn penalty(
     req: HttpRequest,
     session: Session,
 ) -> impl Responder {
-    let mut context = data.lock().unwrap();
-    let (room_id, player_id) = room_and_player(req, session);
-    let room = context.rooms.get_mut(&room_id).unwrap();
-    let game_state = (&room.state).clone().unwrap();
-
-    if game_state == GameState::Plus2 {
-        do_penalty(room, player_id, 2 * room.chain_count);
-    } else if game_state == GameState::Plus4 {
-        do_penalty(room, player_id, 4 * room.chain_count);
-    } else if game_state == GameState::Stop {
-        room.state = None;
-        room.next();
-    }
-
-    notify(&mut context, room_id).await;
-
-    ""
+    act(data, req, session, Action::Penalty).await
 }
 
-pub fn do_penalty(room: &mut Room, player_id: u32, amount: u8) {
-    let mut cards = vec![];
+pub async fn pass(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+) -> impl Responder {
+    act(data, req, session, Action::Pass).await
+}
 
-    for _ in 0..amount {
-        cards.push(room.draw());
+// Cards taken from the deck, each with the player who took it.
+type Draws = Vec<(u32, (u8, Card))>;
+
+// The dealt deck and every card taken from it since: draws, penalties, and
+// cards house cards and scripts made players draw.
+fn revealed(events: Vec<Event>) -> (Vec<(u8, Card)>, Draws) {
+    let mut deck = vec![];
+    let mut draws = vec![];
+
+    for event in events {
+        let player_id = event.player_id;
+
+        match event.kind {
+            EventKind::Deal { deck: dealt } => deck = dealt,
+            EventKind::Draw { card } => draws.push((player_id, card)),
+            EventKind::Penalty { cards } => {
+                draws.extend(cards.into_iter().map(|card| (player_id, card)))
+            }
+            EventKind::Drawn { player, cards } => {
+                draws.extend(cards.into_iter().map(|card| (player, card)))
+            }
+            _ => {}
+        }
     }
 
-    let player = room.player(player_id);
-    player.hand = vec![player.hand.clone(), cards].concat();
-    room.state = None;
-    room.chain_count = 0;
-    room.next();
+    (deck, draws)
 }
 
-pub async fn pass(
+#[derive(Serialize)]
+struct Reveal {
+    pub seed: u64,
+    pub commitment: Option<String>,
+    pub deck: Vec<(u8, Card)>,
+    pub draws: Draws,
+}
+
+pub async fn reveal(
     data: web::Data<Mutex<Uno>>,
     req: HttpRequest,
     session: Session,
 ) -> impl Responder {
-    let mut context = data.lock().unwrap();
+    let context = data.lock().unwrap();
     let (room_id, player_id) = room_and_player(req, session);
-    let room = context.rooms.get_mut(&room_id).unwrap();
-    let player = room.player(player_id);
 
-    if !player.drawed {
-        return Err(actix_web::error::ErrorBadRequest("You must draw one card"));
+    let room = match context.rooms.get(&room_id) {
+        Some(room) => room,
+        None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+    };
+
+    if !room.players.iter().any(|player| player.id == player_id) {
+        return Err(actix_web::error::ErrorUnauthorized("Player not in room"));
     }
 
-    player.drawed = false;
-    room.next();
-    notify(&mut context, room_id).await;
+    if !room.is_over() {
+        return Err(actix_web::error::ErrorBadRequest("The round is not over"));
+    }
 
-    Ok("")
+    let events = context
+        .storage
+        .events(room_id)
+        .map_err(|error| actix_web::error::ErrorInternalServerError(error.0))?;
+    let (deck, draws) = revealed(events);
+
+    Ok(web::Json(Reveal {
+        seed: room.seed,
+        commitment: room.commitment.clone(),
+        deck,
+        draws,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::all;
+    use crate::house::{CardDef, Effect};
+    use crate::room::Room;
+
+    #[test]
+    fn every_card_taken_from_the_deck_is_revealed() {
+        let mut room = Room::new();
+        room.seed = 9;
+        room.house = vec![CardDef {
+            name: "Two more".to_string(),
+            colored: true,
+            copies: 1,
+            effects: vec![Effect::NextDraws { count: 2 }],
+        }];
+        room.seat(User::new("a".to_string(), 1));
+        room.seat(User::new("b".to_string(), 2));
+        let mut events = Engine::deal(&mut room, 1, &all()).unwrap();
+        let dealt = room.deck.clone();
+
+        // Whoever starts holds a house card of the top card's color.
+        let player_id = room.current().id;
+        let color = room.top().1.get_color();
+        let card_id = room.player(player_id).hand[0].0;
+        room.player(player_id).hand[0].1 = Card::Custom("Two more".to_string(), color);
+        room.state = None;
+
+        events.extend(Engine::apply(&mut room, player_id, Action::Draw).unwrap());
+        events.extend(Engine::apply(&mut room, player_id, Action::Pass).unwrap());
+        let other = room.current().id;
+        events.extend(Engine::apply(&mut room, other, Action::Draw).unwrap());
+        events.extend(Engine::apply(&mut room, other, Action::Pass).unwrap());
+        events.extend(
+            Engine::apply(
+                &mut room,
+                player_id,
+                Action::Play {
+                    card_id,
+                    color: None,
+                },
+            )
+            .unwrap(),
+        );
+
+        let (deck, draws) = revealed(events);
+        let taken: Vec<u8> = dealt[..dealt.len() - room.deck.len()]
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+
+        assert_eq!(deck.len(), 54 + 4);
+        assert_eq!(draws.len(), 4);
+        assert_eq!(
+            draws.iter().map(|(_, (id, _))| *id).collect::<Vec<u8>>(),
+            taken
+        );
+        assert_eq!(draws[2].0, other);
+        assert_eq!(draws[3].0, other);
+    }
 }
@@ -0,0 +1,276 @@
+use crate::card::Card;
+use crate::room::Room;
+use crate::strategy::COLORS;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const MAX_COPIES: u8 = 8;
+const MAX_CARDS: usize = 64;
+
+// What a house card does once played, in order. "The next player" is whoever
+// would play next at that point, so a Skip before NextDraws makes the player
+// after the skipped one draw.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum Effect {
+    Skip,
+    Reverse,
+    SwapHands,
+    NextDraws { count: u8 },
+    EveryoneDraws { count: u8 },
+}
+
+// A colored card comes in every color and matches its color or its name,
+// like the built-in action cards. Otherwise it is a wild that asks for a
+// color. `copies` is per color for colored cards.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct CardDef {
+    pub name: String,
+    #[serde(default)]
+    pub colored: bool,
+    #[serde(default = "one")]
+    pub copies: u8,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+fn one() -> u8 {
+    1
+}
+
+#[derive(Deserialize)]
+struct HouseCards {
+    cards: Vec<CardDef>,
+}
+
+pub fn validate(cards: &[CardDef]) -> Result<(), String> {
+    for card in cards {
+        if card.name.trim().is_empty() {
+            return Err("House cards need a name".to_string());
+        }
+
+        if card.copies == 0 || card.copies > MAX_COPIES {
+            return Err(format!(
+                "{} must have between 1 and {} copies",
+                card.name, MAX_COPIES
+            ));
+        }
+
+        if cards.iter().filter(|other| other.name == card.name).count() > 1 {
+            return Err(format!("{} is defined twice", card.name));
+        }
+    }
+
+    if deck(cards).len() > MAX_CARDS {
+        return Err(format!(
+            "House cards can't add more than {} cards",
+            MAX_CARDS
+        ));
+    }
+
+    Ok(())
+}
+
+// Reads `{"cards": [...]}`, or `[[cards]]` tables from a .toml file.
+pub fn parse(path: &Path) -> Result<Vec<CardDef>, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let house: HouseCards = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&text).map_err(|error| error.to_string())?,
+        _ => serde_json::from_str(&text).map_err(|error| error.to_string())?,
+    };
+
+    validate(&house.cards)?;
+    Ok(house.cards)
+}
+
+// The server's own house cards, from the file in UNO_HOUSE_CARDS.
+pub fn load() -> Result<Vec<CardDef>, String> {
+    match env::var("UNO_HOUSE_CARDS") {
+        Ok(path) if !path.is_empty() => parse(Path::new(&path)),
+        _ => Ok(vec![]),
+    }
+}
+
+pub fn deck(cards: &[CardDef]) -> Vec<Card> {
+    cards
+        .iter()
+        .flat_map(|card| {
+            let faces: Vec<Card> = if card.colored {
+                COLORS
+                    .iter()
+                    .map(|color| Card::Custom(card.name.clone(), Some(color.clone())))
+                    .collect()
+            } else {
+                vec![Card::Custom(card.name.clone(), None)]
+            };
+
+            (0..card.copies).flat_map(move |_| faces.clone())
+        })
+        .collect()
+}
+
+pub fn draw(room: &mut Room, player_id: u32, count: u8) {
+    let cards: Vec<(u8, Card)> = (0..count).filter_map(|_| room.draw()).collect();
+    room.player(player_id).hand.extend(cards.clone());
+    room.drawn.push((player_id, cards));
+    room.mercy(player_id);
+}
+
+// Runs the effects of a house card just after `player_id` played it.
+pub fn apply(room: &mut Room, player_id: u32, name: &str) {
+    let effects = match room.house.iter().find(|card| card.name == name) {
+        Some(card) => card.effects.clone(),
+        None => return,
+    };
+
+    for effect in effects {
+        match effect {
+            Effect::Skip => room.next(),
+            // Like a Reverse card, a skip when only two are in play.
+            Effect::Reverse => {
+                room.direction = !room.direction;
+                if room.order().players() == 2 {
+                    room.next();
+                }
+            }
+            // Whoever went out with this card keeps their empty hand.
+            Effect::SwapHands if room.player(player_id).hand.is_empty() => {}
+            Effect::SwapHands => {
+                let next = room.following();
+                let mine = std::mem::take(&mut room.player(player_id).hand);
+                let theirs = std::mem::replace(&mut room.player(next).hand, mine);
+                room.player(player_id).hand = theirs;
+            }
+            Effect::NextDraws { count } => {
+                let next = room.following();
+                draw(room, next, count);
+            }
+            Effect::EveryoneDraws { count } => {
+                let others: Vec<u32> = room
+                    .players
+                    .iter()
+                    .filter(|player| player.id != player_id && !player.hand.is_empty())
+                    .map(|player| player.id)
+                    .collect();
+
+                for other in others {
+                    draw(room, other, count);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardColor;
+    use crate::engine::tests::{current, play, red, table};
+    use crate::engine::{Action, Engine};
+    use crate::event::EventKind;
+
+    fn house(name: &str, effects: Vec<Effect>) -> (CardDef, Card) {
+        let def = CardDef {
+            name: name.to_string(),
+            colored: true,
+            copies: 1,
+            effects,
+        };
+        (def, Card::Custom(name.to_string(), Some(CardColor::Red)))
+    }
+
+    #[test]
+    fn house_skips_come_before_the_turn_moves_on() {
+        let (def, card) = house("Skip", vec![Effect::Skip]);
+        let mut room = table(vec![
+            vec![card.clone(), red(1)],
+            vec![red(2)],
+            vec![red(3)],
+            vec![red(4)],
+        ]);
+        room.house = vec![def];
+        play(&mut room, 1, &card, None);
+        assert_eq!(current(&room), 3);
+    }
+
+    #[test]
+    fn house_reverses_turn_around_like_reverse_cards() {
+        let (def, card) = house("Back", vec![Effect::Reverse]);
+        let mut room = table(vec![vec![card.clone(), red(1)], vec![red(2)], vec![red(3)]]);
+        room.house = vec![def.clone()];
+        play(&mut room, 1, &card, None);
+        assert!(room.direction);
+        assert_eq!(current(&room), 2);
+
+        let mut room = table(vec![vec![card.clone(), red(1)], vec![red(2)]]);
+        room.house = vec![def];
+        play(&mut room, 1, &card, None);
+        assert_eq!(current(&room), 1);
+    }
+
+    #[test]
+    fn swap_hands_trades_with_the_next_player() {
+        let (def, card) = house("Swap", vec![Effect::SwapHands]);
+        let mut room = table(vec![
+            vec![card.clone(), red(1), red(2)],
+            vec![red(3)],
+            vec![red(4)],
+        ]);
+        room.house = vec![def];
+        play(&mut room, 1, &card, None);
+
+        assert_eq!(room.players[0].hand.len(), 1);
+        assert_eq!(room.players[2].hand.len(), 2);
+        assert!(!room.is_over());
+    }
+
+    #[test]
+    fn swap_hands_as_the_last_card_keeps_the_hand_empty() {
+        let (def, card) = house("Swap", vec![Effect::SwapHands]);
+        let mut room = table(vec![vec![card.clone()], vec![red(3)], vec![red(4)]]);
+        room.house = vec![def];
+        play(&mut room, 1, &card, None);
+
+        assert!(room.players[0].hand.is_empty());
+        assert_eq!(room.players[2].hand.len(), 1);
+        assert_eq!(room.finished, vec![1]);
+    }
+
+    #[test]
+    fn house_draws_are_logged() {
+        let (def, card) = house(
+            "Draws",
+            vec![
+                Effect::NextDraws { count: 2 },
+                Effect::EveryoneDraws { count: 1 },
+            ],
+        );
+        let mut room = table(vec![vec![card.clone(), red(1)], vec![red(2)], vec![red(3)]]);
+        room.house = vec![def];
+        let card_id = room.players[0].hand[0].0;
+
+        let events = Engine::apply(
+            &mut room,
+            1,
+            Action::Play {
+                card_id,
+                color: None,
+            },
+        )
+        .unwrap();
+        let drawn: Vec<(u32, usize)> = events
+            .iter()
+            .filter_map(|event| match &event.kind {
+                EventKind::Drawn { player, cards } => Some((*player, cards.len())),
+                _ => None,
+            })
+            .collect();
+
+        assert!(matches!(events[0].kind, EventKind::Play { .. }));
+        assert_eq!(drawn, vec![(3, 2), (2, 1), (3, 1)]);
+        assert_eq!(room.players[2].hand.len(), 4);
+        assert!(room.drawn.is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+pub mod admin;
+pub mod assets;
+pub mod bot;
+pub mod card;
+pub mod chat;
+pub mod engine;
+pub mod event;
+pub mod game;
+pub mod house;
+pub mod matchmaking;
+pub mod rating;
+pub mod room;
+pub mod rules;
+pub mod script;
+pub mod sim;
+pub mod stats;
+pub mod storage;
+pub mod strategy;
+pub mod tournament;
+pub mod turn;
+pub mod uno;
+pub mod user;
@@ -1,23 +1,61 @@
-mod card;
-mod game;
-mod room;
-mod uno;
-mod user;
-
 use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
 use actix_web::{cookie::Key, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
-use room::Room;
+use rust_uno::event::{Event, EventKind};
+use rust_uno::house::{self, CardDef};
+use rust_uno::room::{RuleSet, Teams, TEAMS, TEAM_SIZE};
+use rust_uno::script;
+use rust_uno::storage::{MemoryStorage, SqliteStorage, Storage};
+use rust_uno::strategy::BotKind;
+use rust_uno::uno::{self, get_user, notify, room_and_player, Uno};
+use rust_uno::user::User;
+use rust_uno::{admin, assets, bot, chat, game, matchmaking, rating, stats, tournament};
 use serde::{Deserialize, Serialize};
-use std::sync::{Mutex, MutexGuard};
-use uno::{room_and_player, Uno};
-use user::User;
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let data = web::Data::new(Mutex::new(Uno::new()));
+    let storage: Box<dyn Storage> = match env::var("UNO_DATABASE") {
+        Ok(path) => Box::new(SqliteStorage::open(&path).expect("Could not open database")),
+        Err(_) => Box::new(MemoryStorage::new()),
+    };
+    let mut uno = Uno::restore(storage).expect("Could not restore saved games");
+    uno.house = house::load().expect("Could not load house cards");
+    script::load().expect("Could not load scripts");
+    let playing: Vec<u32> = uno
+        .rooms
+        .iter()
+        .filter(|(_, room)| room.active)
+        .map(|(room_id, _)| *room_id)
+        .collect();
+    let data = web::Data::new(Mutex::new(uno));
+    let static_dir = assets::directory();
+
+    if static_dir.is_none() {
+        eprintln!("No frontend found, serving the API only");
+    }
+
+    // Bots in games saved before a restart wait for nobody to move them.
+    for room_id in playing {
+        actix_web::rt::spawn(bot::run(data.clone(), room_id));
+    }
+
+    // Waiting players' rating windows widen over time, so the queue is
+    // checked every second and not only when someone joins it.
+    let matchmaker = data.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+            let updates = matchmaking::run(&mut matchmaker.lock().unwrap());
+            matchmaking::tell(updates).await;
+        }
+    });
 
     HttpServer::new(move || {
-        App::new()
+        let app = App::new()
             .app_data(data.clone())
             .wrap(
                 // create cookie based session middleware
@@ -30,11 +68,75 @@ async fn main() -> std::io::Result<()> {
             .route("/api/room/{room_id}", web::get().to(room))
             .route("/api/state/{room_id}", web::get().to(state))
             .route("/api/join-room/{room_id}", web::post().to(join_room))
+            .route("/api/add-bot/{room_id}", web::post().to(add_bot))
+            .route("/api/team/{room_id}", web::post().to(pick_team))
             .route("/api/play/{room_id}", web::get().to(game::play))
             .route("/api/turn/{room_id}", web::post().to(game::turn))
             .route("/api/draw/{room_id}", web::post().to(game::draw))
             .route("/api/penalty/{room_id}", web::post().to(game::penalty))
             .route("/api/pass/{room_id}", web::post().to(game::pass))
+            .route("/api/reveal/{room_id}", web::get().to(game::reveal))
+            .route(
+                "/api/legal-moves/{room_id}",
+                web::get().to(game::legal_moves),
+            )
+            .route("/api/chat/{room_id}", web::get().to(chat::history))
+            .route("/api/chat/{room_id}", web::post().to(chat::say))
+            .route("/api/react/{room_id}", web::post().to(chat::react))
+            .route(
+                "/api/users/{user_id}/stats",
+                web::get().to(stats::user_stats),
+            )
+            .route(
+                "/api/users/{user_id}/matches",
+                web::get().to(stats::history),
+            )
+            .route("/api/leaderboard", web::get().to(rating::leaderboard))
+            .route("/api/queue", web::get().to(matchmaking::status))
+            .route("/api/queue", web::post().to(matchmaking::enqueue))
+            .route("/api/queue", web::delete().to(matchmaking::leave))
+            .route("/api/queue/updates", web::get().to(matchmaking::updates))
+            .route("/api/tournaments", web::get().to(tournament::list))
+            .route("/api/tournaments", web::post().to(tournament::create))
+            .route(
+                "/api/tournaments/{tournament_id}",
+                web::get().to(tournament::bracket),
+            )
+            .route(
+                "/api/tournaments/{tournament_id}/join",
+                web::post().to(tournament::join),
+            )
+            .route(
+                "/api/tournaments/{tournament_id}/start",
+                web::post().to(tournament::start),
+            )
+            .route(
+                "/api/tournaments/{tournament_id}/no-show",
+                web::post().to(tournament::no_show),
+            )
+            .route("/api/mute/{room_id}", web::post().to(chat::mute))
+            .route(
+                "/api/admin/rooms/{room_id}",
+                web::get().to(admin::export_room),
+            )
+            .route("/api/admin/rooms", web::post().to(admin::import_room))
+            .route(
+                "/api/admin/rooms/{room_id}/events",
+                web::get().to(admin::events),
+            )
+            .route(
+                "/api/admin/rooms/{room_id}/seed",
+                web::post().to(admin::seed),
+            )
+            .route(
+                "/api/admin/rooms/{room_id}/replay/{step}",
+                web::get().to(admin::replay),
+            );
+
+        match &static_dir {
+            Some(dir) => app.service(assets::files(dir.clone())),
+            None => app,
+        }
     })
     .bind(("0.0.0.0", 8080))?
     .run()
@@ -52,6 +154,12 @@ async fn index(data: web::Data<Mutex<Uno>>, session: Session) -> impl Responder
 #[derive(Deserialize)]
 struct NewRoomReq {
     pub username: String,
+    #[serde(default)]
+    pub rules: RuleSet,
+    #[serde(default)]
+    pub teams: Option<Teams>,
+    pub house_cards: Option<Vec<CardDef>>,
+    pub script: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -65,31 +173,51 @@ async fn new_room(
     form: web::Json<NewRoomReq>,
 ) -> impl Responder {
     let mut context = data.lock().unwrap();
-    context.room_index += 1;
-    let user = get_user(&mut context, form, session);
-    let room_index = context.room_index;
-    context.rooms.insert(room_index, Room::new().push(user));
-    web::Json(NewRoomRes {
-        room_id: context.room_index,
-    })
-}
+    // Rooms get the server's house cards unless they bring their own. Flip
+    // has a deck of its own and leaves them out.
+    let house_cards = match (&form.house_cards, &form.rules) {
+        (Some(cards), _) => cards.clone(),
+        (None, RuleSet::Flip) => vec![],
+        (None, _) => context.house.clone(),
+    };
 
-fn get_user(context: &mut MutexGuard<Uno>, form: web::Json<NewRoomReq>, session: Session) -> User {
-    let mut player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+    if !house_cards.is_empty() {
+        if form.rules == RuleSet::Flip {
+            return Err(actix_web::error::ErrorBadRequest(
+                "House cards can't be used with Flip",
+            ));
+        }
 
-    if player_id == 0 {
-        context.user_index += 1;
-        player_id = context.user_index;
-        session.insert("player_id", player_id).unwrap();
+        house::validate(&house_cards).map_err(actix_web::error::ErrorBadRequest)?;
+    }
+
+    if let Some(name) = &form.script {
+        if !script::exists(name) {
+            return Err(actix_web::error::ErrorBadRequest("Unknown script"));
+        }
+    }
+
+    let user = get_user(&mut context, &form.username, &session);
+    let room_id = context.create_room(form.rules.clone());
+
+    if !house_cards.is_empty() {
+        context.rooms.get_mut(&room_id).unwrap().house = house_cards.clone();
+        let house = EventKind::House { cards: house_cards };
+        context.save_room(room_id, vec![Event::new(0, house)]);
     }
 
-    if context.users.get(&player_id).is_none() {
-        context
-            .users
-            .insert(player_id, User::new(form.username.clone(), player_id));
+    if let Some(name) = form.script.clone() {
+        context.rooms.get_mut(&room_id).unwrap().script = Some(name.clone());
+        context.save_room(room_id, vec![Event::new(0, EventKind::Script { name })]);
     }
 
-    context.users.get(&player_id).unwrap().clone()
+    if let Some(teams) = form.teams.clone() {
+        context.rooms.get_mut(&room_id).unwrap().teams = Some(teams.clone());
+        context.save_room(room_id, vec![Event::new(0, EventKind::Teams { teams })]);
+    }
+
+    context.join(room_id, user);
+    Ok(web::Json(NewRoomRes { room_id }))
 }
 
 async fn room(data: web::Data<Mutex<Uno>>, req: HttpRequest, session: Session) -> impl Responder {
@@ -101,7 +229,7 @@ async fn room(data: web::Data<Mutex<Uno>>, req: HttpRequest, session: Session) -
         return Err(actix_web::error::ErrorUnauthorized("Player not in room"));
     }
 
-    Ok(web::Json(room.clone()))
+    Ok(web::Json(room.view(player_id)))
 }
 
 async fn join_room(
@@ -110,26 +238,136 @@ async fn join_room(
     session: Session,
     form: web::Json<NewRoomReq>,
 ) -> impl Responder {
-    let mut context = data.lock().unwrap();
-    let room_id = req
-        .match_info()
-        .get("room_id")
-        .unwrap()
-        .parse::<u32>()
-        .unwrap();
+    let (res, sockets) = {
+        let mut context = data.lock().unwrap();
+        let room_id = req
+            .match_info()
+            .get("room_id")
+            .unwrap()
+            .parse::<u32>()
+            .unwrap();
 
-    let user = get_user(&mut context, form, session);
-    let room = context.rooms.get_mut(&room_id).unwrap();
-    room.players.push(user);
+        let user = get_user(&mut context, &form.username, &session);
+        context.join(room_id, user);
 
-    let subscribers = context.subscribers.get_mut(&room_id).unwrap();
-    for subscription in subscribers.iter_mut() {
-        subscription.session.text("update").await.unwrap();
-    }
+        let res = NewRoomRes {
+            room_id: context.room_index,
+        };
+        (res, context.sockets(room_id))
+    };
 
-    web::Json(NewRoomRes {
-        room_id: context.room_index,
-    })
+    notify(sockets).await;
+    web::Json(res)
+}
+
+#[derive(Deserialize)]
+struct AddBotReq {
+    pub kind: BotKind,
+}
+
+async fn add_bot(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<AddBotReq>,
+) -> impl Responder {
+    let sockets = {
+        let mut context = data.lock().unwrap();
+        let (room_id, player_id) = room_and_player(req, session);
+
+        let room = match context.rooms.get(&room_id) {
+            Some(room) => room,
+            None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+        };
+
+        if !room.players.iter().any(|player| player.id == player_id) {
+            return Err(actix_web::error::ErrorUnauthorized("Player not in room"));
+        }
+
+        if room.active {
+            return Err(actix_web::error::ErrorBadRequest("Game already started"));
+        }
+
+        if !form.kind.is_available() {
+            return Err(actix_web::error::ErrorBadRequest("Unknown bot"));
+        }
+
+        context.user_index += 1;
+        let bot_id = context.user_index;
+        let name = format!("Bot {}", bot_id);
+        let bot = User::bot(name.clone(), bot_id, form.kind.clone());
+        context.rooms.get_mut(&room_id).unwrap().seat(bot);
+
+        let kind = Some(form.kind.clone());
+        let join = EventKind::Join { name, bot: kind };
+        context.save_room(room_id, vec![Event::new(bot_id, join)]);
+        context.sockets(room_id)
+    };
+
+    notify(sockets).await;
+    Ok("")
+}
+
+#[derive(Deserialize)]
+struct PickTeamReq {
+    pub team: u8,
+    pub player_id: Option<u32>,
+}
+
+// Players pick their own team. The host can also move bots around.
+async fn pick_team(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<PickTeamReq>,
+) -> impl Responder {
+    let sockets = {
+        let mut context = data.lock().unwrap();
+        let (room_id, player_id) = room_and_player(req, session);
+
+        let room = match context.rooms.get_mut(&room_id) {
+            Some(room) => room,
+            None => return Err(actix_web::error::ErrorNotFound("Room does not exist")),
+        };
+
+        if room.teams.is_none() {
+            return Err(actix_web::error::ErrorBadRequest("This room has no teams"));
+        }
+
+        if room.active {
+            return Err(actix_web::error::ErrorBadRequest("Game already started"));
+        }
+
+        let target = form.player_id.unwrap_or(player_id);
+        let allowed = match room.players.iter().find(|player| player.id == target) {
+            Some(player) => {
+                target == player_id || (player.bot.is_some() && room.host() == Some(player_id))
+            }
+            None => return Err(actix_web::error::ErrorBadRequest("Player not in room")),
+        };
+
+        if !allowed {
+            return Err(actix_web::error::ErrorUnauthorized(
+                "You can only move yourself or, as the host, a bot",
+            ));
+        }
+
+        if form.team >= TEAMS {
+            return Err(actix_web::error::ErrorBadRequest("Unknown team"));
+        }
+
+        if room.player(target).team != Some(form.team) && room.team_size(form.team) >= TEAM_SIZE {
+            return Err(actix_web::error::ErrorBadRequest("That team is full"));
+        }
+
+        room.player(target).team = Some(form.team);
+        let pick = EventKind::PickTeam { team: form.team };
+        context.save_room(room_id, vec![Event::new(target, pick)]);
+        context.sockets(room_id)
+    };
+
+    notify(sockets).await;
+    Ok("")
 }
 
 async fn state(
@@ -147,13 +385,9 @@ async fn state(
     let (res, session, _stream) = actix_ws::handle(&req, stream)?;
     let subscribers = &mut data.lock().unwrap().subscribers;
 
-    if !subscribers.contains_key(&room_id) {
-        subscribers.insert(room_id, vec![]);
-    }
-
     subscribers
-        .get_mut(&room_id)
-        .unwrap()
+        .entry(room_id)
+        .or_default()
         .push(uno::UnoSocket { session });
 
     Ok::<HttpResponse, actix_web::Error>(res)
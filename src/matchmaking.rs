@@ -0,0 +1,340 @@
+use crate::event::now;
+use crate::room::RuleSet;
+use crate::uno::{broadcast, get_user, Uno, UnoSocket};
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+// Players are matched with others within WINDOW rating points. The window
+// widens the longer they wait so nobody is stuck in the queue forever.
+const WINDOW: f64 = 100.0;
+const WINDOW_PER_SECOND: f64 = 10.0;
+
+#[derive(Clone, Serialize, Debug)]
+pub struct Ticket {
+    pub player_id: u32,
+    pub players: usize,
+    pub rules: RuleSet,
+    pub rating: f64,
+    pub since: u64,
+}
+
+impl Ticket {
+    fn window(&self, at: u64) -> f64 {
+        WINDOW + WINDOW_PER_SECOND * (at.saturating_sub(self.since) / 1000) as f64
+    }
+
+    fn accepts(&self, other: &Ticket, at: u64) -> bool {
+        let difference = (self.rating - other.rating).abs();
+        self.players == other.players
+            && self.rules == other.rules
+            && difference <= self.window(at)
+            && difference <= other.window(at)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Queue {
+    pub tickets: Vec<Ticket>,
+}
+
+impl Queue {
+    pub fn enqueue(&mut self, ticket: Ticket) {
+        self.leave(ticket.player_id);
+        self.tickets.push(ticket);
+    }
+
+    pub fn leave(&mut self, player_id: u32) -> bool {
+        let before = self.tickets.len();
+        self.tickets.retain(|ticket| ticket.player_id != player_id);
+        before != self.tickets.len()
+    }
+
+    pub fn ticket(&self, player_id: u32) -> Option<&Ticket> {
+        self.tickets
+            .iter()
+            .find(|ticket| ticket.player_id == player_id)
+    }
+
+    // The player who has waited longest gets a table first, with the
+    // compatible players closest to their rating. Everyone at the table has
+    // to be within everyone else's window, not just the first player's.
+    pub fn find_match(&mut self, at: u64) -> Option<Vec<Ticket>> {
+        let mut tickets = self.tickets.clone();
+        tickets.sort_by_key(|ticket| ticket.since);
+
+        for anchor in tickets.iter() {
+            let mut others: Vec<&Ticket> = tickets
+                .iter()
+                .filter(|other| other.player_id != anchor.player_id)
+                .filter(|other| anchor.accepts(other, at))
+                .collect();
+
+            if others.len() + 1 < anchor.players {
+                continue;
+            }
+
+            others.sort_by(|a, b| {
+                let a = (a.rating - anchor.rating).abs();
+                let b = (b.rating - anchor.rating).abs();
+                a.partial_cmp(&b).unwrap()
+            });
+
+            let mut group = vec![anchor.clone()];
+            for other in others {
+                if group.len() == anchor.players {
+                    break;
+                }
+                if group.iter().all(|member| member.accepts(other, at)) {
+                    group.push(other.clone());
+                }
+            }
+
+            if group.len() < anchor.players {
+                continue;
+            }
+
+            for ticket in group.iter() {
+                self.leave(ticket.player_id);
+            }
+
+            return Some(group);
+        }
+
+        None
+    }
+}
+
+#[derive(Serialize)]
+struct Matched {
+    pub room_id: u32,
+}
+
+#[derive(Serialize)]
+struct MatchedUpdate {
+    pub matched: Matched,
+}
+
+// Seats every group the queue can form in a new room, the same way players
+// join by hand. Returns who to tell about it.
+pub fn run(context: &mut Uno) -> Vec<(Vec<UnoSocket>, String)> {
+    let mut updates = vec![];
+
+    while let Some(group) = context.queue.find_match(now()) {
+        let room_id = context.create_room(group[0].rules.clone());
+        let update = serde_json::to_string(&MatchedUpdate {
+            matched: Matched { room_id },
+        })
+        .unwrap();
+
+        for ticket in group {
+            if let Some(user) = context.users.get(&ticket.player_id).cloned() {
+                context.join(room_id, user);
+            }
+
+            context.matched.insert(ticket.player_id, room_id);
+            let sockets = context
+                .queue_subscribers
+                .remove(&ticket.player_id)
+                .unwrap_or_default();
+            updates.push((sockets, update.clone()));
+        }
+    }
+
+    updates
+}
+
+pub async fn tell(updates: Vec<(Vec<UnoSocket>, String)>) {
+    for (sockets, update) in updates {
+        broadcast(sockets, &update).await;
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QueueReq {
+    pub username: String,
+    pub players: usize,
+    #[serde(default)]
+    pub rules: RuleSet,
+}
+
+pub async fn enqueue(
+    data: web::Data<Mutex<Uno>>,
+    session: Session,
+    form: web::Json<QueueReq>,
+) -> impl Responder {
+    let max = form.rules.max_players();
+    if form.players < 2 || form.players > max {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "Games are for 2 to {} players",
+            max
+        )));
+    }
+
+    let updates = {
+        let mut context = data.lock().unwrap();
+        let user = get_user(&mut context, &form.username, &session);
+        let rating = context.ratings.get(user.id);
+
+        context.matched.remove(&user.id);
+        context.queue.enqueue(Ticket {
+            player_id: user.id,
+            players: form.players,
+            rules: form.rules.clone(),
+            rating,
+            since: now(),
+        });
+
+        run(&mut context)
+    };
+
+    tell(updates).await;
+    Ok("")
+}
+
+pub async fn leave(data: web::Data<Mutex<Uno>>, session: Session) -> impl Responder {
+    let mut context = data.lock().unwrap();
+    let player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+
+    if !context.queue.leave(player_id) {
+        return Err(actix_web::error::ErrorBadRequest(
+            "You are not in the queue",
+        ));
+    }
+
+    Ok("")
+}
+
+#[derive(Serialize)]
+struct QueueStatus {
+    pub ticket: Option<Ticket>,
+    pub waited: u64,
+    pub room_id: Option<u32>,
+}
+
+pub async fn status(data: web::Data<Mutex<Uno>>, session: Session) -> impl Responder {
+    let context = data.lock().unwrap();
+    let player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+    let ticket = context.queue.ticket(player_id).cloned();
+
+    web::Json(QueueStatus {
+        waited: ticket
+            .as_ref()
+            .map_or(0, |ticket| now().saturating_sub(ticket.since)),
+        ticket,
+        room_id: context.matched.get(&player_id).cloned(),
+    })
+}
+
+// Tells the player `{"matched": {"room_id": ...}}` once they have a seat.
+pub async fn updates(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    stream: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    let player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+    let (res, session, _stream) = actix_ws::handle(&req, stream)?;
+
+    data.lock()
+        .unwrap()
+        .queue_subscribers
+        .entry(player_id)
+        .or_default()
+        .push(UnoSocket { session });
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(player_id: u32, rating: f64, since: u64) -> Ticket {
+        Ticket {
+            player_id,
+            players: 3,
+            rules: RuleSet::Classic,
+            rating,
+            since,
+        }
+    }
+
+    fn ids(group: Option<Vec<Ticket>>) -> Vec<u32> {
+        group
+            .unwrap_or_default()
+            .iter()
+            .map(|ticket| ticket.player_id)
+            .collect()
+    }
+
+    #[test]
+    fn windows_widen_while_players_wait() {
+        let mut queue = Queue::default();
+        queue.enqueue(Ticket {
+            players: 2,
+            ..ticket(1, 1000.0, 0)
+        });
+        queue.enqueue(Ticket {
+            players: 2,
+            ..ticket(2, 1150.0, 0)
+        });
+
+        assert!(queue.find_match(1_000).is_none());
+        assert!(queue.find_match(4_999).is_none());
+        assert_eq!(ids(queue.find_match(5_000)), vec![1, 2]);
+        assert!(queue.tickets.is_empty());
+    }
+
+    #[test]
+    fn everyone_at_the_table_is_within_each_others_window() {
+        let mut queue = Queue::default();
+        queue.enqueue(ticket(1, 1000.0, 0));
+        queue.enqueue(ticket(2, 910.0, 1));
+        queue.enqueue(ticket(3, 1090.0, 2));
+
+        // Both are close to the first player but 180 points apart.
+        assert!(queue.find_match(0).is_none());
+
+        queue.enqueue(ticket(4, 1050.0, 3));
+        assert_eq!(ids(queue.find_match(0)), vec![1, 4, 3]);
+        assert_eq!(ids(Some(queue.tickets.clone())), vec![2]);
+    }
+
+    #[test]
+    fn only_players_after_the_same_game_are_grouped() {
+        let mut queue = Queue::default();
+        queue.enqueue(ticket(1, 1000.0, 0));
+        queue.enqueue(Ticket {
+            rules: RuleSet::Flip,
+            ..ticket(2, 1000.0, 1)
+        });
+        queue.enqueue(Ticket {
+            players: 2,
+            ..ticket(3, 1000.0, 2)
+        });
+        queue.enqueue(ticket(4, 1000.0, 3));
+        assert!(queue.find_match(0).is_none());
+
+        queue.enqueue(ticket(5, 1000.0, 4));
+        assert_eq!(ids(queue.find_match(0)), vec![1, 4, 5]);
+    }
+
+    #[test]
+    fn players_who_leave_are_not_matched() {
+        let mut queue = Queue::default();
+        queue.enqueue(ticket(1, 1000.0, 0));
+        queue.enqueue(ticket(2, 1000.0, 1));
+
+        assert!(queue.leave(1));
+        assert!(!queue.leave(1));
+        assert!(queue.ticket(1).is_none());
+
+        queue.enqueue(ticket(3, 1000.0, 2));
+        assert!(queue.find_match(0).is_none());
+
+        queue.enqueue(ticket(1, 1000.0, 3));
+        assert_eq!(ids(queue.find_match(0)), vec![2, 3, 1]);
+    }
+}
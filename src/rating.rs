@@ -0,0 +1,309 @@
+use crate::event::now;
+use crate::stats::{pages, MatchRecord};
+use crate::uno::Uno;
+use actix_web::{web, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub const INITIAL: f64 = 1500.0;
+const K: f64 = 32.0;
+const DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RatingChange {
+    pub before: f64,
+    pub after: f64,
+}
+
+// Current rating of every account that has played a rated match. The history
+// lives in the match records, so this is rebuilt from them on restart.
+#[derive(Debug, Default)]
+pub struct Ratings {
+    current: HashMap<u32, f64>,
+}
+
+impl Ratings {
+    pub fn restore(matches: &[MatchRecord]) -> Ratings {
+        let mut ratings = Ratings::default();
+
+        for record in matches {
+            for result in record.players.iter() {
+                if let Some(change) = &result.rating {
+                    ratings.current.insert(result.player_id, change.after);
+                }
+            }
+        }
+
+        ratings
+    }
+
+    pub fn get(&self, player_id: u32) -> f64 {
+        self.current.get(&player_id).cloned().unwrap_or(INITIAL)
+    }
+
+    // Multiplayer Elo: every pair of rated players counts as one game won by
+    // whoever placed better, and the K factor is split between the N - 1
+    // opponents so a round moves a rating about as much as a duel would.
+    // Bots and guests are left out; at least two rated players are needed.
+    pub fn rate(&mut self, record: &mut MatchRecord, rated: impl Fn(u32) -> bool) {
+        let players: Vec<(u32, u32, f64)> = record
+            .players
+            .iter()
+            .filter(|result| rated(result.player_id))
+            .map(|result| {
+                let rating = self.get(result.player_id);
+                (result.player_id, result.placement, rating)
+            })
+            .collect();
+
+        if players.len() < 2 {
+            return;
+        }
+
+        let k = K / (players.len() - 1) as f64;
+
+        for (player_id, placement, rating) in players.iter() {
+            let delta: f64 = players
+                .iter()
+                .filter(|(other, _, _)| other != player_id)
+                .map(|(_, other_placement, other_rating)| {
+                    let expected = 1.0 / (1.0 + 10f64.powf((other_rating - rating) / 400.0));
+                    let score = match placement.cmp(other_placement) {
+                        std::cmp::Ordering::Less => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Greater => 0.0,
+                    };
+                    k * (score - expected)
+                })
+                .sum();
+
+            let change = RatingChange {
+                before: *rating,
+                after: rating + delta,
+            };
+
+            self.current.insert(*player_id, change.after);
+
+            if let Some(result) = record
+                .players
+                .iter_mut()
+                .find(|result| result.player_id == *player_id)
+            {
+                result.rating = Some(change);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub period: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Standing {
+    pub rank: usize,
+    pub player_id: u32,
+    pub name: String,
+    pub rating: f64,
+    pub games: u32,
+    pub change: f64,
+}
+
+#[derive(Serialize)]
+struct Leaderboard {
+    pub period: String,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+    pub standings: Vec<Standing>,
+}
+
+// `all` ranks by current rating. `day`, `week` and `month` only list players
+// with a rated match in that window and rank them by the rating they gained.
+pub async fn leaderboard(
+    data: web::Data<Mutex<Uno>>,
+    query: web::Query<LeaderboardQuery>,
+) -> impl Responder {
+    let context = data.lock().unwrap();
+    let period = query.period.clone().unwrap_or_else(|| "all".to_string());
+    let since = match period.as_str() {
+        "all" => None,
+        "day" => Some(now().saturating_sub(DAY)),
+        "week" => Some(now().saturating_sub(7 * DAY)),
+        "month" => Some(now().saturating_sub(30 * DAY)),
+        _ => return Err(actix_web::error::ErrorBadRequest("Unknown period")),
+    };
+
+    let mut totals: HashMap<u32, (u32, f64)> = HashMap::new();
+
+    for record in context.matches.iter() {
+        if since.is_some_and(|since| record.ended_at < since) {
+            continue;
+        }
+
+        for result in record.players.iter() {
+            if let Some(change) = &result.rating {
+                let total = totals.entry(result.player_id).or_default();
+                total.0 += 1;
+                total.1 += change.after - change.before;
+            }
+        }
+    }
+
+    let mut standings: Vec<Standing> = totals
+        .into_iter()
+        .map(|(player_id, (games, change))| Standing {
+            rank: 0,
+            player_id,
+            name: context
+                .users
+                .get(&player_id)
+                .map(|user| user.name.clone())
+                .unwrap_or_default(),
+            rating: context.ratings.get(player_id),
+            games,
+            change,
+        })
+        .collect();
+
+    standings.sort_by(|a, b| {
+        let (a_key, b_key) = match since {
+            None => (a.rating, b.rating),
+            Some(_) => (a.change, b.change),
+        };
+        b_key
+            .partial_cmp(&a_key)
+            .unwrap()
+            .then(a.player_id.cmp(&b.player_id))
+    });
+
+    for (index, standing) in standings.iter_mut().enumerate() {
+        standing.rank = index + 1;
+    }
+
+    let (page, per_page) = pages(query.page, query.per_page);
+
+    Ok(web::Json(Leaderboard {
+        period,
+        page,
+        per_page,
+        total: standings.len(),
+        standings: standings
+            .into_iter()
+            .skip(page.saturating_sub(1).saturating_mul(per_page))
+            .take(per_page)
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::tests::result;
+
+    // One result per (player_id, placement).
+    fn record(placements: &[(u32, u32)]) -> MatchRecord {
+        MatchRecord {
+            room_id: 1,
+            ended_at: 0,
+            players: placements
+                .iter()
+                .map(|(player_id, placement)| result(*player_id, *placement))
+                .collect(),
+        }
+    }
+
+    fn change(record: &MatchRecord, player_id: u32) -> f64 {
+        let rating = record.result(player_id).unwrap().rating.clone().unwrap();
+        rating.after - rating.before
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn a_duel_between_equals_moves_half_of_k() {
+        let mut ratings = Ratings::default();
+        let mut duel = record(&[(1, 1), (2, 2)]);
+        ratings.rate(&mut duel, |_| true);
+
+        assert!(close(change(&duel, 1), K / 2.0));
+        assert!(close(change(&duel, 2), -K / 2.0));
+        assert!(close(ratings.get(1), INITIAL + K / 2.0));
+        assert!(close(ratings.get(3), INITIAL));
+    }
+
+    #[test]
+    fn k_is_split_between_the_opponents() {
+        let mut ratings = Ratings::default();
+        let mut round = record(&[(1, 1), (2, 2), (3, 3), (4, 4)]);
+        ratings.rate(&mut round, |_| true);
+
+        assert!(close(change(&round, 1), K / 2.0));
+        assert!(close(change(&round, 2), K / 6.0));
+        assert!(close(change(&round, 3), -K / 6.0));
+        assert!(close(change(&round, 4), -K / 2.0));
+    }
+
+    #[test]
+    fn an_upset_moves_more_than_the_expected_result() {
+        let mut ratings = Ratings::default();
+        ratings.current.insert(1, 1800.0);
+
+        let mut expected = record(&[(1, 1), (2, 2)]);
+        ratings.rate(&mut expected, |_| true);
+        let gained = change(&expected, 1);
+
+        ratings.current.insert(1, 1800.0);
+        ratings.current.insert(2, INITIAL);
+        let mut upset = record(&[(2, 1), (1, 2)]);
+        ratings.rate(&mut upset, |_| true);
+
+        assert!(gained > 0.0 && gained < K / 2.0);
+        assert!(change(&upset, 2) > K / 2.0);
+        assert!(close(change(&upset, 1) + change(&upset, 2), 0.0));
+    }
+
+    #[test]
+    fn shared_placements_count_as_draws() {
+        let mut ratings = Ratings::default();
+        let mut teams = record(&[(1, 1), (2, 1), (3, 2), (4, 2)]);
+        ratings.rate(&mut teams, |_| true);
+
+        assert!(close(change(&teams, 1), change(&teams, 2)));
+        assert!(close(change(&teams, 1), K / 3.0));
+        assert!(close(change(&teams, 3), -K / 3.0));
+    }
+
+    #[test]
+    fn unrated_players_are_left_out() {
+        let mut ratings = Ratings::default();
+        let mut round = record(&[(1, 1), (2, 2), (3, 3)]);
+        ratings.rate(&mut round, |player_id| player_id != 2);
+
+        assert_eq!(round.result(2).unwrap().rating, None);
+        assert!(close(change(&round, 1), K / 2.0));
+
+        let mut alone = record(&[(1, 1), (2, 2)]);
+        ratings.rate(&mut alone, |player_id| player_id == 1);
+        assert!(alone.players.iter().all(|result| result.rating.is_none()));
+    }
+
+    #[test]
+    fn ratings_are_restored_from_the_last_match() {
+        let mut ratings = Ratings::default();
+        let mut first = record(&[(1, 1), (2, 2)]);
+        let mut second = record(&[(2, 1), (1, 2)]);
+        ratings.rate(&mut first, |_| true);
+        ratings.rate(&mut second, |_| true);
+
+        let restored = Ratings::restore(&[first, second]);
+        assert!(close(restored.get(1), ratings.get(1)));
+        assert!(close(restored.get(2), ratings.get(2)));
+    }
+}
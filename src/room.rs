@@ -1,10 +1,58 @@
-use crate::card::{Card, CardColor, GameState};
+use crate::card::{all, flip, no_mercy, Card, CardColor, GameState};
+use crate::chat::Chat;
+use crate::house::{self, CardDef};
+use crate::rules::effects;
+use crate::script;
+use crate::strategy::{BotKind, COLORS, DARK_COLORS};
+use crate::turn::{opening, Advance, TurnOrder};
 use crate::user::User;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use serde::Serialize;
+use rand::{random, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 
-#[derive(Clone, Serialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RuleSet {
+    #[default]
+    Classic,
+    Flip,
+    NoMercy,
+}
+
+impl RuleSet {
+    // How many players the rules' own deck can deal to. House cards only
+    // add to it, see Room::max_players.
+    pub fn max_players(&self) -> usize {
+        let deck = match self {
+            RuleSet::Classic => all().len(),
+            RuleSet::Flip => flip().len(),
+            RuleSet::NoMercy => no_mercy().len(),
+        };
+        max_players(deck)
+    }
+}
+
+// Everyone is dealt seven cards and one more starts the discard pile.
+pub fn max_players(deck: usize) -> usize {
+    deck.saturating_sub(1) / 7
+}
+
+// Two teams of two, partners sitting opposite each other. With open hands
+// partners can see each other's cards.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Teams {
+    pub open_hands: bool,
+}
+
+// No Mercy knocks out anyone holding this many cards.
+pub const MERCY: usize = 25;
+
+pub const TEAMS: u8 = 2;
+pub const TEAM_SIZE: usize = 2;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Room {
     pub players: Vec<User>,
     pub active: bool,
@@ -16,6 +64,92 @@ pub struct Room {
     pub state: Option<GameState>,
     pub chain_count: u8,
     pub direction: bool,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    shuffles: u64,
+    #[serde(default)]
+    pub commitment: Option<String>,
+    #[serde(default)]
+    pub chat: Chat,
+    #[serde(default)]
+    pub ended: bool,
+    #[serde(default)]
+    pub rules: RuleSet,
+    #[serde(default)]
+    pub teams: Option<Teams>,
+    // The hidden side of every card, by card id, when playing Flip.
+    #[serde(default)]
+    faces: Vec<Card>,
+    #[serde(default)]
+    pub dark: bool,
+    // Cards waiting for whoever can't stack any more, in No Mercy.
+    #[serde(default)]
+    pub pending: u8,
+    #[serde(default)]
+    pub eliminated: Vec<u32>,
+    // Who emptied their hand, in the order they went out.
+    #[serde(default)]
+    pub finished: Vec<u32>,
+    #[serde(default)]
+    pub house: Vec<CardDef>,
+    // The name of a server script hooked into the game, see script.rs.
+    #[serde(default)]
+    pub script: Option<String>,
+    // Points handed out by the script, by player.
+    #[serde(default)]
+    pub scores: HashMap<u32, i32>,
+    // The first player seated, who created the room. Seating teams moves
+    // players around, so it is kept apart.
+    #[serde(default)]
+    host: Option<u32>,
+    // Draws made by house cards and scripts since the last action, waiting
+    // to be logged.
+    #[serde(skip)]
+    pub drawn: Vec<(u32, Vec<(u8, Card)>)>,
+}
+
+// A seat as other players see it: how many cards it holds, and the cards
+// themselves only where the viewer may see them.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlayerView {
+    pub name: String,
+    pub id: u32,
+    pub cards: usize,
+    pub hand: Option<Vec<(u8, Card)>>,
+    pub drawed: bool,
+    pub bot: Option<BotKind>,
+    pub team: Option<u8>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RoomView {
+    pub players: Vec<PlayerView>,
+    pub active: bool,
+    pub deck_size: usize,
+    pub board: Vec<(u8, Card)>,
+    pub current_player: PlayerView,
+    pub color: CardColor,
+    pub state: Option<GameState>,
+    pub chain_count: u8,
+    pub direction: bool,
+    pub commitment: Option<String>,
+    pub seed: Option<u64>,
+    pub over: bool,
+    pub rules: RuleSet,
+    pub teams: Option<Teams>,
+    pub dark: bool,
+    pub pending: u8,
+    pub eliminated: Vec<u32>,
+    pub house: Vec<CardDef>,
+    pub script: Option<String>,
+    pub scores: HashMap<u32, i32>,
+}
+
+impl Default for Room {
+    fn default() -> Room {
+        Room::new()
+    }
 }
 
 impl Room {
@@ -31,7 +165,82 @@ impl Room {
             state: None,
             chain_count: 0,
             direction: false,
+            seed: random(),
+            shuffles: 0,
+            commitment: None,
+            chat: Chat::default(),
+            ended: false,
+            rules: RuleSet::Classic,
+            teams: None,
+            faces: vec![],
+            dark: false,
+            pending: 0,
+            eliminated: vec![],
+            finished: vec![],
+            house: vec![],
+            script: None,
+            scores: HashMap::new(),
+            host: None,
+            drawn: vec![],
+        }
+    }
+
+    // In team mode newcomers join the smaller team; they can switch later.
+    pub fn seat(&mut self, mut user: User) {
+        if self.teams.is_some() {
+            user.team = (0..TEAMS).min_by_key(|team| self.team_size(*team));
+        }
+
+        self.host.get_or_insert(user.id);
+        self.players.push(user);
+    }
+
+    pub fn team_size(&self, team: u8) -> usize {
+        self.players
+            .iter()
+            .filter(|player| player.team == Some(team))
+            .count()
+    }
+
+    pub fn teams_ready(&self) -> bool {
+        (0..TEAMS).all(|team| self.team_size(team) == TEAM_SIZE)
+            && self.players.len() == TEAMS as usize * TEAM_SIZE
+    }
+
+    pub fn partner(&self, player_id: u32) -> Option<&User> {
+        let team = self
+            .players
+            .iter()
+            .find(|player| player.id == player_id)?
+            .team?;
+
+        self.players
+            .iter()
+            .find(|player| player.id != player_id && player.team == Some(team))
+    }
+
+    // Alternates the teams around the table so partners sit opposite.
+    fn seat_teams(&mut self) {
+        let mut teams: Vec<Vec<User>> = (0..TEAMS)
+            .map(|team| {
+                self.players
+                    .iter()
+                    .filter(|player| player.team == Some(team))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+
+        let mut seats = vec![];
+        for _ in 0..TEAM_SIZE {
+            for team in teams.iter_mut() {
+                if !team.is_empty() {
+                    seats.push(team.remove(0));
+                }
+            }
         }
+
+        self.players = seats;
     }
 
     pub fn push(&mut self, user: User) -> Room {
@@ -46,57 +255,604 @@ impl Room {
             .unwrap()
     }
 
+    // Whoever created the room sits first.
+    // Rooms saved before the host was kept fall back to the first seat.
+    pub fn host(&self) -> Option<u32> {
+        self.host
+            .or_else(|| self.players.first().map(|player| player.id))
+    }
+
+    pub fn current(&self) -> &User {
+        &self.players[self.turn as usize]
+    }
+
     pub fn update_player(&mut self) {
-        self.current_player = self.players[self.turn as usize].clone();
+        self.current_player = self.current().clone();
     }
 
-    pub fn to_center(&mut self) {
+    pub fn put_center(&mut self) {
         self.board.push(self.deck.pop().unwrap());
     }
 
-    pub fn draw(&mut self) -> (u8, Card) {
-        if self.deck.len() < 2 {
-            self.deck = vec![self.board.clone(), self.deck.clone()].concat();
-            let mut rng = thread_rng();
-            self.deck.shuffle(&mut rng);
+    fn shuffle(&mut self) {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(self.shuffles);
+        self.shuffles += 1;
+        self.deck.shuffle(&mut rng);
+    }
+
+    pub fn max_players(&self, cards: &[Card]) -> usize {
+        let deck = match self.rules {
+            RuleSet::Classic => cards.len() + house::deck(&self.house).len(),
+            RuleSet::NoMercy => no_mercy().len() + house::deck(&self.house).len(),
+            RuleSet::Flip => flip().len(),
+        };
+        max_players(deck)
+    }
+
+    // Flip brings its own double-sided deck and starts on the light side.
+    // House cards are shuffled into the others.
+    pub fn deal(&mut self, cards: &[Card]) -> Vec<(u8, Card)> {
+        let cards = match self.rules {
+            RuleSet::Classic => [cards.to_vec(), house::deck(&self.house)].concat(),
+            RuleSet::NoMercy => [no_mercy(), house::deck(&self.house)].concat(),
+            RuleSet::Flip => {
+                let (light, dark): (Vec<Card>, Vec<Card>) = flip().into_iter().unzip();
+                self.faces = dark;
+                light
+            }
+        };
+        self.dark = false;
+
+        self.deck = cards
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i as u8, c.clone()))
+            .collect();
+        self.shuffle();
+        let deck = self.deck.clone();
+        self.commitment = Some(commit(self.seed, &deck));
+
+        if self.teams.is_some() {
+            self.seat_teams();
         }
 
-        let result = self.deck[0].clone();
-        self.deck = self.deck[1..].to_vec();
-        result
+        for player in self.players.iter_mut() {
+            let rest = self.deck.split_off(7);
+            player.hand = std::mem::replace(&mut self.deck, rest);
+        }
+
+        self.put_center();
+
+        let advance = loop {
+            match opening(&self.top().1) {
+                Some(advance) => break advance,
+                None if self.deck.is_empty() => break Advance::Stay,
+                None => self.put_center(),
+            }
+        };
+
+        // The first card takes effect as if played just before the first
+        // player's turn.
+        effects(self, self.top(), None);
+        self.advance(advance);
+        self.active = true;
+        self.update_player();
+        deck
     }
 
-    pub fn top(&self) -> (u8, Card) {
-        self.board[self.board.len() - 1].clone()
+    // Turns every card over: the draw pile, the discard pile and the hands.
+    pub fn flip(&mut self) {
+        let faces = &mut self.faces;
+        let mut turn_over = |cards: &mut Vec<(u8, Card)>| {
+            for (id, card) in cards.iter_mut() {
+                std::mem::swap(card, &mut faces[*id as usize]);
+            }
+        };
+
+        turn_over(&mut self.deck);
+        turn_over(&mut self.board);
+        for player in self.players.iter_mut() {
+            turn_over(&mut player.hand);
+        }
+
+        self.dark = !self.dark;
+        self.update_player();
     }
 
-    pub fn left(&mut self) {
-        if self.turn == 0 {
-            self.turn = self.players.len() as u8 - 1;
+    // The mercy rule: a player with too many cards is out of the round and
+    // their hand goes under the draw pile.
+    pub fn mercy(&mut self, player_id: u32) -> bool {
+        if self.rules != RuleSet::NoMercy || self.player(player_id).hand.len() < MERCY {
+            return false;
+        }
+
+        let player = self.player(player_id);
+        let mut hand = std::mem::take(&mut player.hand);
+        player.drawed = false;
+        self.deck.append(&mut hand);
+        self.eliminated.push(player_id);
+        true
+    }
+
+    pub fn colors(&self) -> &'static [CardColor; 4] {
+        if self.dark {
+            &DARK_COLORS
         } else {
-            self.turn -= 1;
+            &COLORS
         }
     }
 
-    pub fn right(&mut self) {
-        if self.turn == self.players.len() as u8 - 1 {
-            self.turn = 0;
+    fn recycle(&mut self) {
+        let top = self.board.pop();
+        self.deck.append(&mut self.board);
+        self.board.extend(top);
+    }
+
+    // Cards that can still be drawn, counting the board under the top card
+    // that gets shuffled back in.
+    pub fn cards_left(&self) -> usize {
+        self.deck.len() + self.board.len().saturating_sub(1)
+    }
+
+    pub fn draw(&mut self) -> Option<(u8, Card)> {
+        if self.deck.len() < 2 {
+            self.recycle();
+            self.shuffle();
+        }
+
+        if self.deck.is_empty() {
+            None
         } else {
-            self.turn += 1;
+            Some(self.deck.remove(0))
         }
     }
 
-    pub fn next(&mut self) {
-        if self.direction {
-            self.right();
+    // A team wins as soon as either partner empties their hand.
+    // Players knocked out by the mercy rule hold no cards either, but only
+    // emptying a hand wins it for a team.
+    pub fn is_over(&self) -> bool {
+        let order = self.order();
+        let emptied = self
+            .players
+            .iter()
+            .any(|player| player.hand.is_empty() && !self.eliminated.contains(&player.id));
+        let in_play = |team: u8| {
+            self.players
+                .iter()
+                .zip(order.in_play.iter())
+                .any(|(player, in_play)| player.team == Some(team) && *in_play)
+        };
+
+        self.active
+            && match self.teams {
+                Some(_) => emptied || !(0..TEAMS).all(in_play),
+                None => order.players() <= 1,
+            }
+    }
+
+    // The first player to empty their hand or, when everybody else was
+    // knocked out, the last one standing.
+    pub fn winner(&self) -> Option<u32> {
+        if let Some(first) = self.finished.first() {
+            return Some(*first);
+        }
+
+        let order = self.order();
+        match order.players() {
+            1 => order
+                .in_play
+                .iter()
+                .position(|in_play| *in_play)
+                .map(|seat| self.players[seat].id),
+            _ => None,
+        }
+    }
+
+    // True exactly once, the first time it is asked after the round is over,
+    // so the results get recorded a single time.
+    pub fn end_round(&mut self) -> bool {
+        if self.is_over() && !self.ended {
+            self.ended = true;
+            script::on_round_end(self, self.winner());
+            true
         } else {
-            self.left();
+            false
+        }
+    }
+
+    // Whether `viewer` may see `player_id`'s cards: their own, and their
+    // partner's with open hands.
+    fn shows_hand(&self, viewer: u32, player_id: u32) -> bool {
+        let open_hands = self.teams.as_ref().is_some_and(|teams| teams.open_hands);
+
+        player_id == viewer
+            || (open_hands
+                && self
+                    .partner(viewer)
+                    .is_some_and(|partner| partner.id == player_id))
+    }
+
+    fn player_view(&self, viewer: u32, player: &User) -> PlayerView {
+        PlayerView {
+            name: player.name.clone(),
+            id: player.id,
+            cards: player.hand.len(),
+            hand: if self.shows_hand(viewer, player.id) {
+                Some(player.hand.clone())
+            } else {
+                None
+            },
+            drawed: player.drawed,
+            bot: player.bot.clone(),
+            team: player.team,
+        }
+    }
+
+    // The room as `viewer` sees it, with everybody else's cards hidden.
+    pub fn view(&self, viewer: u32) -> RoomView {
+        let over = self.is_over();
+
+        RoomView {
+            players: self
+                .players
+                .iter()
+                .map(|player| self.player_view(viewer, player))
+                .collect(),
+            active: self.active,
+            deck_size: self.deck.len(),
+            board: self.board.clone(),
+            current_player: self.player_view(viewer, &self.current_player),
+            color: self.color.clone(),
+            state: self.state.clone(),
+            chain_count: self.chain_count,
+            direction: self.direction,
+            commitment: self.commitment.clone(),
+            seed: if over { Some(self.seed) } else { None },
+            over,
+            rules: self.rules.clone(),
+            teams: self.teams.clone(),
+            dark: self.dark,
+            pending: self.pending,
+            eliminated: self.eliminated.clone(),
+            house: self.house.clone(),
+            script: self.script.clone(),
+            scores: self.scores.clone(),
+        }
+    }
+
+    pub fn top(&self) -> (u8, Card) {
+        self.board[self.board.len() - 1].clone()
+    }
+
+    // Seats holding cards that are still in the round.
+    pub fn order(&self) -> TurnOrder {
+        TurnOrder {
+            in_play: self
+                .players
+                .iter()
+                .map(|player| !player.hand.is_empty() && !self.eliminated.contains(&player.id))
+                .collect(),
+            turn: self.turn as usize,
+            direction: self.direction,
+        }
+    }
+
+    pub fn advance(&mut self, advance: Advance) {
+        let mut order = self.order();
+        order.advance(advance);
+        self.turn = order.turn as u8;
+    }
+
+    pub fn next(&mut self) {
+        self.advance(Advance::Next);
+    }
+
+    // Who plays after the current player, without moving the turn.
+    pub fn following(&self) -> u32 {
+        self.players[self.order().following()].id
+    }
+}
+
+pub fn commit(seed: u64, deck: &[(u8, Card)]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+
+    for (id, _) in deck {
+        hasher.update([*id]);
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Action, Engine};
+    use crate::rules::penalty_amount;
+
+    fn dealt(seed: u64) -> (Room, Vec<(u8, Card)>) {
+        let mut room = Room::new();
+        room.seed = seed;
+        room.seat(User::new("a".to_string(), 1));
+        room.seat(User::new("b".to_string(), 2));
+        room.seat(User::new("c".to_string(), 3));
+        let deck = room.deal(&all());
+        (room, deck)
+    }
+
+    #[test]
+    fn the_commitment_hashes_the_seed_and_card_ids() {
+        let deck = vec![(3, Card::Plus4), (0, Card::ChangeColor), (41, Card::Plus4)];
+        let mut bytes = 0x0102030405060708u64.to_be_bytes().to_vec();
+        bytes.extend([3, 0, 41]);
+
+        assert_eq!(
+            commit(0x0102030405060708, &deck),
+            hex::encode(Sha256::digest(&bytes))
+        );
+        assert_ne!(commit(1, &deck), commit(2, &deck));
+        assert_ne!(commit(1, &deck), commit(1, &deck[1..]));
+    }
+
+    #[test]
+    fn deal_commits_to_the_dealt_deck() {
+        let (room, deck) = dealt(42);
+
+        assert_eq!(room.commitment, Some(commit(42, &deck)));
+        assert_eq!(deck.len(), all().len());
+    }
+
+    #[test]
+    fn the_same_seed_deals_the_same_cards() {
+        let (first, first_deck) = dealt(9);
+        let (second, second_deck) = dealt(9);
+        let (_, other_deck) = dealt(10);
+
+        assert_eq!(first_deck, second_deck);
+        assert_eq!(first.commitment, second.commitment);
+        assert_ne!(first_deck, other_deck);
+    }
+
+    #[test]
+    fn hands_and_board_follow_the_committed_deck() {
+        let (room, deck) = dealt(5);
+
+        for (seat, player) in room.players.iter().enumerate() {
+            assert_eq!(player.hand, deck[seat * 7..seat * 7 + 7].to_vec());
+        }
+
+        let turned = deck.len() - room.deck.len() - 21;
+        let mut board = deck[deck.len() - turned..].to_vec();
+        board.reverse();
+        assert_eq!(room.board, board);
+    }
+
+    #[test]
+    fn the_seed_is_revealed_only_once_the_round_is_over() {
+        let (mut room, _) = dealt(77);
+        assert_eq!(room.view(1).seed, None);
+
+        room.player(1).hand.clear();
+        room.player(2).hand.clear();
+        assert_eq!(room.view(1).seed, Some(77));
+    }
+
+    #[test]
+    fn the_deck_is_dealt_to_at_most_seven_classic_players() {
+        assert_eq!(RuleSet::Classic.max_players(), 7);
+        assert_eq!(Room::new().max_players(&all()), 7);
+
+        for seed in 0..200 {
+            let mut room = Room::new();
+            room.seed = seed;
+            for id in 1..=7 {
+                room.seat(User::new(id.to_string(), id));
+            }
+            room.deal(&all());
+
+            assert!(room.players.iter().all(|player| player.hand.len() == 7));
+            assert_eq!(room.deck.len() + room.board.len(), 54 - 49);
+        }
+    }
+
+    #[test]
+    fn the_view_only_shows_the_callers_hand() {
+        let (room, _) = dealt(5);
+        let view = room.view(2);
+
+        for (player, seen) in room.players.iter().zip(view.players.iter()) {
+            assert_eq!(seen.cards, 7);
+            let hand = (player.id == 2).then(|| player.hand.clone());
+            assert_eq!(seen.hand, hand);
+        }
+
+        assert_eq!(view.current_player.id, room.current_player.id);
+        assert_eq!(
+            view.current_player.hand.is_some(),
+            room.current_player.id == 2
+        );
+    }
+
+    #[test]
+    fn open_hands_show_the_partners_hand() {
+        for open_hands in [false, true] {
+            let mut room = Room::new();
+            room.teams = Some(Teams { open_hands });
+            for id in 1..=4 {
+                room.seat(User::new(id.to_string(), id));
+            }
+            room.deal(&all());
+
+            let partner = room.partner(1).unwrap().id;
+            for seen in room.view(1).players {
+                let shown = seen.id == 1 || (open_hands && seen.id == partner);
+                assert_eq!(seen.hand.is_some(), shown, "{} {}", seen.id, open_hands);
+            }
         }
+    }
+
+    #[test]
+    fn the_host_keeps_the_room_when_teams_are_seated() {
+        let mut room = Room::new();
+        room.teams = Some(Teams { open_hands: false });
+        for id in 1..=4 {
+            room.seat(User::new(id.to_string(), id));
+        }
+        room.player(1).team = Some(1);
+        room.player(2).team = Some(0);
+        room.player(3).team = Some(1);
+        room.player(4).team = Some(0);
+        room.deal(&all());
+
+        assert_ne!(room.players[0].id, 1);
+        assert_eq!(room.host(), Some(1));
+    }
+
+    #[test]
+    fn knocked_out_players_dont_end_the_round() {
+        let mut room = Room::new();
+        room.rules = RuleSet::NoMercy;
+        room.teams = Some(Teams { open_hands: false });
+        for id in 1..=4 {
+            room.seat(User::new(id.to_string(), id));
+        }
+        room.deal(&all());
+
+        let partner = room.partner(1).unwrap().id;
+        room.player(1).hand.clear();
+        room.eliminated.push(1);
+        assert!(!room.is_over());
+
+        // With a whole team out, the other one wins.
+        room.player(partner).hand.clear();
+        room.eliminated.push(partner);
+        assert!(room.is_over());
+    }
+
+    #[test]
+    fn the_round_goes_on_while_two_players_hold_cards() {
+        let (mut room, _) = dealt(3);
+        room.player(1).hand.clear();
+        room.eliminated.push(1);
+        assert!(!room.is_over());
 
-        let player = &self.players[self.turn as usize];
+        room.player(2).hand.clear();
+        assert!(room.is_over());
+    }
+
+    #[test]
+    fn the_winner_went_out_first_or_stood_last() {
+        let (mut room, _) = dealt(4);
+        assert_eq!(room.winner(), None);
+
+        room.players[2].hand.clear();
+        room.finished.push(room.players[2].id);
+        room.players[0].hand.clear();
+        room.finished.push(room.players[0].id);
+        assert_eq!(room.winner(), Some(room.players[2].id));
+
+        let (mut room, _) = dealt(4);
+        for seat in [0, 2] {
+            let id = room.players[seat].id;
+            room.player(id).hand.clear();
+            room.eliminated.push(id);
+        }
+        assert_eq!(room.winner(), Some(room.players[1].id));
+    }
+
+    // Deals a room with each seed until every kind of opening card has come up.
+    fn openings(players: u32, rules: RuleSet) -> Vec<Room> {
+        (0..400)
+            .map(|seed| {
+                let mut room = Room::new();
+                room.seed = seed;
+                room.rules = rules.clone();
+
+                for id in 1..=players {
+                    room.seat(User::new(format!("p{}", id), id));
+                }
+
+                room.deal(&all());
+                room
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_opening_card_takes_effect() {
+        for players in [2, 3, 4] {
+            let mut seen = vec![];
+
+            for room in openings(players, RuleSet::Classic) {
+                let top = room.top().1;
+                let (seat, direction, state) = match top {
+                    Card::Number(_, _) => (0, false, None),
+                    Card::Stop(_) => (players - 1, false, None),
+                    Card::Reverse(_) => (1, true, None),
+                    Card::Plus2(_) => (0, false, Some(GameState::Plus2)),
+                    _ => panic!("{:?} opened", top),
+                };
+
+                assert_eq!(
+                    room.current().id,
+                    seat + 1,
+                    "{:?} with {} players",
+                    top,
+                    players
+                );
+                assert_eq!(room.direction, direction);
+                assert_eq!(room.state, state);
+                assert_eq!(room.current_player.id, room.current().id);
+                seen.push(std::mem::discriminant(&top));
+            }
+
+            seen.sort_by_key(|kind| format!("{:?}", kind));
+            seen.dedup();
+            assert_eq!(
+                seen.len(),
+                4,
+                "not every opening came up with {} players",
+                players
+            );
+        }
+    }
+
+    #[test]
+    fn an_opening_plus_2_is_the_first_players_penalty() {
+        let room = openings(3, RuleSet::Classic)
+            .into_iter()
+            .find(|room| room.top().1.is_plus_2())
+            .unwrap();
+
+        assert_eq!(room.current().id, 1);
+        assert_eq!(penalty_amount(&room), Some(2));
+        assert!(Engine::legal_actions(&room, 1).contains(&Action::Penalty));
+        assert!(!Engine::legal_actions(&room, 1).contains(&Action::Draw));
+    }
+
+    #[test]
+    fn an_opening_plus_2_stacks_in_no_mercy() {
+        let room = openings(3, RuleSet::NoMercy)
+            .into_iter()
+            .find(|room| room.top().1.is_plus_2())
+            .unwrap();
+
+        assert_eq!(room.state, Some(GameState::Stack));
+        assert_eq!(penalty_amount(&room), Some(2));
+    }
 
-        if player.hand.len() == 0 {
-            self.next();
+    #[test]
+    fn special_cards_never_open() {
+        for rules in [RuleSet::Classic, RuleSet::NoMercy, RuleSet::Flip] {
+            for room in openings(3, rules.clone()) {
+                assert!(
+                    opening(&room.top().1).is_some(),
+                    "{:?} opened {:?}",
+                    room.top().1,
+                    rules
+                );
+            }
         }
     }
 }
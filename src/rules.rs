@@ -0,0 +1,165 @@
+use crate::card::{Card, CardColor, GameState};
+use crate::event::EventKind;
+use crate::house;
+use crate::room::{Room, RuleSet};
+use crate::script;
+use crate::turn::after_play;
+
+pub fn check_move(room: &Room, card: &Card) -> Option<String> {
+    let top = room.top().1;
+
+    let choosen_color = if top.is_color_card() {
+        room.color.clone()
+    } else {
+        CardColor::Red
+    };
+
+    top.matches(room.state.clone(), choosen_color, card.clone())
+}
+
+pub fn do_play(
+    room: &mut Room,
+    player_id: u32,
+    card: (u8, Card),
+    color: Option<CardColor>,
+) -> Vec<EventKind> {
+    effects(room, card.clone(), color.clone());
+    do_turn(room, player_id, card.clone());
+
+    let mut events = vec![EventKind::Play { card: card.clone() }];
+    if let (true, Some(color)) = (card.1.is_color_card(), color) {
+        events.push(EventKind::ChooseColor { color });
+    }
+
+    events
+}
+
+pub fn effects(room: &mut Room, card_tuple: (u8, Card), color: Option<CardColor>) {
+    if let (true, Some(color)) = (card_tuple.1.is_color_card(), color) {
+        room.color = color;
+    }
+
+    // In No Mercy every draw card stacks on the ones before it.
+    if room.rules == RuleSet::NoMercy && card_tuple.1.draws() > 0 {
+        room.state = Some(GameState::Stack);
+        room.chain_count += 1;
+        room.pending += card_tuple.1.draws();
+    } else if card_tuple.1.can_chain() {
+        room.state = card_tuple.1.to_game_state();
+        room.chain_count += 1;
+    } else {
+        room.chain_count = 0;
+    }
+
+    if card_tuple.1.is_reverse() {
+        room.direction = !room.direction;
+    }
+}
+
+pub fn do_turn(room: &mut Room, player_id: u32, card_tuple: (u8, Card)) {
+    let players = room.order().players();
+    let player = room.player(player_id);
+    player.hand = player
+        .hand
+        .clone()
+        .into_iter()
+        .filter(|(id, _)| *id != card_tuple.0)
+        .collect();
+    player.drawed = false;
+    let card = card_tuple.1.clone();
+
+    // Discard All takes every other card of its color along, under it.
+    if card.is_discard_all() {
+        let color = card.clone().get_color();
+        let (discarded, kept) = player
+            .hand
+            .clone()
+            .into_iter()
+            .partition(|(_, card)| card.clone().get_color() == color);
+        player.hand = kept;
+        room.board.extend::<Vec<(u8, Card)>>(discarded);
+    }
+
+    let emptied = room.player(player_id).hand.is_empty();
+    room.board.push(card_tuple);
+
+    if emptied {
+        room.finished.push(player_id);
+    }
+
+    if card.is_flip() {
+        room.flip();
+    }
+
+    if let Card::Custom(name, _) = &card {
+        house::apply(room, player_id, name);
+    }
+
+    script::after_play(room, player_id, &card);
+    room.advance(after_play(&card, players, emptied));
+}
+
+pub fn do_draw(room: &mut Room, player_id: u32, card: (u8, Card)) {
+    let player = room.player(player_id);
+    player.hand.push(card.clone());
+    player.drawed = true;
+    script::on_draw(room, player_id, &card.1);
+
+    if room.mercy(player_id) {
+        room.next();
+    }
+}
+
+pub fn penalty_amount(room: &Room) -> Option<u8> {
+    match room.state {
+        Some(GameState::Plus2) => Some(2 * room.chain_count),
+        Some(GameState::Plus4) => Some(4 * room.chain_count),
+        Some(GameState::DrawFive) => Some(5 * room.chain_count),
+        Some(GameState::Stack) => Some(room.pending),
+        Some(GameState::Stop) | Some(GameState::DrawColor) => Some(0),
+        None => None,
+    }
+}
+
+// Draw Color keeps drawing until a card of the chosen color turns up or the
+// cards run out.
+pub fn penalty_cards(room: &mut Room) -> Vec<(u8, Card)> {
+    if room.state != Some(GameState::DrawColor) {
+        let amount = penalty_amount(room).unwrap_or(0);
+        return (0..amount).filter_map(|_| room.draw()).collect();
+    }
+
+    let mut cards = vec![];
+    while let Some(card) = room.draw() {
+        let found = card.1.clone().get_color() == Some(room.color.clone());
+        cards.push(card);
+
+        if found {
+            break;
+        }
+    }
+
+    cards
+}
+
+pub fn do_penalty(room: &mut Room, player_id: u32, cards: Vec<(u8, Card)>) {
+    // Only rooms saved while a Stop was still passed on get here.
+    if room.state == Some(GameState::Stop) {
+        room.state = None;
+        room.next();
+        return;
+    }
+
+    let player = room.player(player_id);
+    player.hand = [player.hand.clone(), cards].concat();
+    room.state = None;
+    room.chain_count = 0;
+    room.pending = 0;
+    room.mercy(player_id);
+    room.next();
+}
+
+pub fn do_pass(room: &mut Room, player_id: u32) {
+    room.player(player_id).drawed = false;
+    room.next();
+}
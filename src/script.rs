@@ -0,0 +1,505 @@
+use crate::card::Card;
+use crate::house;
+use crate::room::Room;
+use crate::strategy::{COLORS, DARK_COLORS};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+use wasmi::{Caller, Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Val};
+
+// Every hook call gets this much fuel, about one unit per instruction, and a
+// fresh instance, so scripts can't loop forever or keep state between calls.
+const FUEL: u64 = 1_000_000;
+const MAX_SIZE: usize = 1 << 20;
+const MAX_MEMORY: usize = 1 << 20;
+const MAX_EFFECTS: usize = 16;
+const MAX_DRAW: i32 = 10;
+
+static SCRIPTS: OnceLock<Scripts> = OnceLock::new();
+
+// The compiled modules and the host functions they import, set up once.
+struct Scripts {
+    linker: Linker<Host>,
+    modules: HashMap<String, Module>,
+}
+
+impl Scripts {
+    fn new(wasm: Vec<(String, Vec<u8>)>) -> Result<Scripts, String> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let linker = linker(&engine).map_err(|error| error.to_string())?;
+        let mut modules = HashMap::new();
+
+        for (name, wasm) in wasm {
+            if wasm.len() > MAX_SIZE {
+                return Err(format!("{} is larger than {} bytes", name, MAX_SIZE));
+            }
+
+            let module =
+                Module::new(&engine, &wasm[..]).map_err(|error| format!("{}: {}", name, error))?;
+            modules.insert(name, module);
+        }
+
+        Ok(Scripts { linker, modules })
+    }
+}
+
+// What a script asked for during a hook. They are applied once the hook has
+// returned, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Effect {
+    Skip,
+    Reverse,
+    Draw { player: u32, count: u8 },
+    Score { player: u32, points: i32 },
+}
+
+// The script's view of the room: a snapshot taken before the hook runs.
+struct Host {
+    limits: StoreLimits,
+    current: u32,
+    next: u32,
+    hands: Vec<(u32, usize)>,
+    effects: Vec<Effect>,
+}
+
+impl Host {
+    fn push(&mut self, effect: Effect) {
+        if self.effects.len() < MAX_EFFECTS {
+            self.effects.push(effect);
+        }
+    }
+}
+
+// Scripts are configured by whoever runs the server, like external bots:
+// UNO_SCRIPTS="name=path/to/script.wasm;other=other.wasm"
+fn compile() -> Result<Scripts, String> {
+    let entries = env::var("UNO_SCRIPTS").unwrap_or_default();
+    let mut wasm = vec![];
+
+    for entry in entries.split(';').filter(|entry| !entry.trim().is_empty()) {
+        let (name, path) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Expected name=path, got {}", entry))?;
+        let bytes = fs::read(path.trim()).map_err(|error| format!("{}: {}", path, error))?;
+        wasm.push((name.trim().to_string(), bytes));
+    }
+
+    Scripts::new(wasm)
+}
+
+pub fn load() -> Result<(), String> {
+    let scripts = compile()?;
+    let _ = SCRIPTS.set(scripts);
+    Ok(())
+}
+
+fn scripts() -> &'static Scripts {
+    SCRIPTS.get_or_init(|| {
+        compile().unwrap_or_else(|error| {
+            eprintln!("Could not load scripts: {}", error);
+            Scripts::new(vec![]).unwrap()
+        })
+    })
+}
+
+pub fn exists(name: &str) -> bool {
+    scripts().modules.contains_key(name)
+}
+
+fn linker(engine: &Engine) -> Result<Linker<Host>, wasmi::Error> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap("uno", "skip", |mut caller: Caller<'_, Host>| {
+        caller.data_mut().push(Effect::Skip)
+    })?;
+    linker.func_wrap("uno", "reverse", |mut caller: Caller<'_, Host>| {
+        caller.data_mut().push(Effect::Reverse)
+    })?;
+    linker.func_wrap(
+        "uno",
+        "draw",
+        |mut caller: Caller<'_, Host>, player: i32, count: i32| {
+            let count = count.clamp(0, MAX_DRAW) as u8;
+            caller.data_mut().push(Effect::Draw {
+                player: player as u32,
+                count,
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "uno",
+        "score",
+        |mut caller: Caller<'_, Host>, player: i32, points: i32| {
+            caller.data_mut().push(Effect::Score {
+                player: player as u32,
+                points,
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "uno",
+        "hand_size",
+        |caller: Caller<'_, Host>, player: i32| {
+            caller
+                .data()
+                .hands
+                .iter()
+                .find(|(id, _)| *id == player as u32)
+                .map(|(_, cards)| *cards as i32)
+                .unwrap_or(-1)
+        },
+    )?;
+    linker.func_wrap("uno", "current_player", |caller: Caller<'_, Host>| {
+        caller.data().current as i32
+    })?;
+    linker.func_wrap("uno", "next_player", |caller: Caller<'_, Host>| {
+        caller.data().next as i32
+    })?;
+
+    Ok(linker)
+}
+
+// Runs one exported hook of the room's script. A room without a script, a
+// script that doesn't export the hook, or one that traps or runs out of fuel
+// all count as doing nothing.
+fn call(room: &Room, hook: &str, args: &[i32]) -> Option<(i32, Vec<Effect>)> {
+    let name = room.script.as_ref()?;
+    let scripts = scripts();
+    let module = scripts.modules.get(name)?;
+
+    let host = Host {
+        limits: StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .memories(1)
+            .tables(1)
+            .table_elements(1024)
+            .instances(1)
+            .build(),
+        current: room.current().id,
+        next: room.following(),
+        hands: room
+            .players
+            .iter()
+            .map(|player| (player.id, player.hand.len()))
+            .collect(),
+        effects: vec![],
+    };
+
+    let mut store = Store::new(module.engine(), host);
+    store.limiter(|host| &mut host.limits);
+
+    let result = store
+        .set_fuel(FUEL)
+        .map_err(|error| error.to_string())
+        .and_then(|_| {
+            scripts
+                .linker
+                .instantiate(&mut store, module)
+                .and_then(|instance| instance.start(&mut store))
+                .map_err(|error| error.to_string())
+        })
+        .and_then(|instance| {
+            let func = match instance.get_func(&store, hook) {
+                Some(func) => func,
+                None => return Ok(None),
+            };
+            let inputs: Vec<Val> = args.iter().map(|arg| Val::I32(*arg)).collect();
+            let mut outputs = vec![Val::I32(0); func.ty(&store).results().len()];
+
+            func.call(&mut store, &inputs, &mut outputs)
+                .map_err(|error| error.to_string())?;
+
+            Ok(Some(outputs.first().and_then(Val::i32).unwrap_or(0)))
+        });
+
+    match result {
+        Ok(Some(value)) => Some((value, store.into_data().effects)),
+        Ok(None) => None,
+        Err(error) => {
+            eprintln!("Script {} failed in {}: {}", name, hook, error);
+            None
+        }
+    }
+}
+
+// Cards go to scripts as three numbers: the kind, in the order of the Card
+// variants, the color, in the order light then dark side, and the number.
+// Missing colors and numbers are -1.
+fn encode(card: &Card) -> [i32; 3] {
+    let kind = match card {
+        Card::Number(_, _) => 0,
+        Card::Stop(_) => 1,
+        Card::Reverse(_) => 2,
+        Card::Plus2(_) => 3,
+        Card::ChangeColor => 4,
+        Card::Plus4 => 5,
+        Card::Flip(_) => 6,
+        Card::DrawFive(_) => 7,
+        Card::SkipEveryone(_) => 8,
+        Card::WildDrawColor => 9,
+        Card::DiscardAll(_) => 10,
+        Card::Plus6 => 11,
+        Card::Plus10 => 12,
+        Card::ReversePlus4 => 13,
+        Card::Custom(_, _) => 14,
+    };
+
+    let color = card
+        .clone()
+        .get_color()
+        .and_then(|color| {
+            COLORS
+                .iter()
+                .chain(DARK_COLORS.iter())
+                .position(|c| *c == color)
+        })
+        .map(|index| index as i32)
+        .unwrap_or(-1);
+
+    let number = match card {
+        Card::Number(number, _) => *number as i32,
+        _ => -1,
+    };
+
+    [kind, color, number]
+}
+
+fn apply(room: &mut Room, effects: Vec<Effect>) {
+    for effect in effects {
+        match effect {
+            Effect::Skip => {
+                let current = room.current().id;
+                room.player(current).drawed = false;
+                room.next();
+            }
+            Effect::Reverse => room.direction = !room.direction,
+            Effect::Draw { player, count } => {
+                let playing = room
+                    .players
+                    .iter()
+                    .any(|other| other.id == player && !other.hand.is_empty());
+
+                if playing {
+                    house::draw(room, player, count);
+                }
+            }
+            Effect::Score { player, points } => {
+                *room.scores.entry(player).or_insert(0) += points;
+            }
+        }
+    }
+}
+
+// Whether the script lets `player_id` play `card`. Returning nonzero from
+// before_play vetoes the move.
+pub fn allows(room: &Room, player_id: u32, card: &Card) -> bool {
+    let [kind, color, number] = encode(card);
+
+    match call(
+        room,
+        "before_play",
+        &[player_id as i32, kind, color, number],
+    ) {
+        Some((veto, _)) => veto == 0,
+        None => true,
+    }
+}
+
+// Right after a card is played, before the turn moves on.
+pub fn after_play(room: &mut Room, player_id: u32, card: &Card) {
+    let [kind, color, number] = encode(card);
+
+    if let Some((_, effects)) = call(room, "after_play", &[player_id as i32, kind, color, number]) {
+        apply(room, effects);
+    }
+}
+
+pub fn on_draw(room: &mut Room, player_id: u32, card: &Card) {
+    let [kind, color, number] = encode(card);
+
+    if let Some((_, effects)) = call(room, "on_draw", &[player_id as i32, kind, color, number]) {
+        apply(room, effects);
+    }
+}
+
+// Once the round is over only scores can change.
+pub fn on_round_end(room: &mut Room, winner: Option<u32>) {
+    let winner = winner.map(|id| id as i32).unwrap_or(-1);
+
+    if let Some((_, effects)) = call(room, "on_round_end", &[winner]) {
+        let scores = effects
+            .into_iter()
+            .filter(|effect| matches!(effect, Effect::Score { .. }))
+            .collect();
+        apply(room, scores);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::engine::tests::{red, table};
+
+    const HOST: &str = r#"
+        (import "uno" "skip" (func $skip))
+        (import "uno" "reverse" (func $reverse))
+        (import "uno" "draw" (func $draw (param i32 i32)))
+        (import "uno" "score" (func $score (param i32 i32)))
+        (import "uno" "next_player" (func $next_player (result i32)))
+    "#;
+
+    // Hooks, each wrapped in a module that can call every host function.
+    const FIXTURES: &[(&str, &str)] = &[
+        (
+            "no_sevens",
+            r#"(func (export "before_play") (param i32 i32 i32 i32) (result i32)
+                (i32.and (i32.eqz (local.get 1)) (i32.eq (local.get 3) (i32.const 7))))"#,
+        ),
+        (
+            "spin",
+            r#"(func (export "before_play") (param i32 i32 i32 i32) (result i32)
+                (loop $forever (br $forever))
+                (i32.const 1))
+               (func (export "after_play") (param i32 i32 i32 i32)
+                (call $skip)
+                (loop $forever (br $forever)))"#,
+        ),
+        (
+            "big",
+            r#"(memory 17)
+               (func (export "before_play") (param i32 i32 i32 i32) (result i32)
+                (i32.const 1))"#,
+        ),
+        (
+            "fits",
+            r#"(memory 16)
+               (func (export "before_play") (param i32 i32 i32 i32) (result i32)
+                (i32.const 1))"#,
+        ),
+        (
+            "greedy",
+            r#"(func (export "after_play") (param i32 i32 i32 i32) (local i32)
+                (loop $again
+                  (call $score (local.get 0) (i32.const 1))
+                  (local.set 4 (i32.add (local.get 4) (i32.const 1)))
+                  (br_if $again (i32.lt_u (local.get 4) (i32.const 40)))))"#,
+        ),
+        (
+            "skip",
+            r#"(func (export "after_play") (param i32 i32 i32 i32) (call $skip))"#,
+        ),
+        (
+            "reverse",
+            r#"(func (export "after_play") (param i32 i32 i32 i32) (call $reverse))"#,
+        ),
+        (
+            "draw",
+            r#"(func (export "after_play") (param i32 i32 i32 i32)
+                (call $draw (call $next_player) (i32.const 2)))"#,
+        ),
+        (
+            "skip_on_draw",
+            r#"(func (export "on_draw") (param i32 i32 i32 i32) (call $skip))"#,
+        ),
+        (
+            "winner",
+            r#"(func (export "on_round_end") (param i32)
+                (call $skip)
+                (call $score (i32.const 1) (local.get 0)))"#,
+        ),
+    ];
+
+    // Registers the fixtures as the server's scripts. Every test that runs a
+    // script calls this first, so the scripts never come from UNO_SCRIPTS.
+    pub(crate) fn install() {
+        SCRIPTS.get_or_init(|| {
+            let wasm = FIXTURES
+                .iter()
+                .map(|(name, hooks)| {
+                    let module = format!("(module {} {})", HOST, hooks);
+                    (name.to_string(), wat::parse_str(module).unwrap())
+                })
+                .collect();
+            Scripts::new(wasm).unwrap()
+        });
+    }
+
+    pub(crate) fn scripted(name: &str, hands: Vec<Vec<Card>>) -> Room {
+        install();
+        let mut room = table(hands);
+        room.script = Some(name.to_string());
+        room
+    }
+
+    #[test]
+    fn before_play_can_refuse_a_card() {
+        let room = scripted("no_sevens", vec![vec![red(7), red(5)], vec![red(1)]]);
+
+        assert!(!allows(&room, 1, &red(7)));
+        assert!(allows(&room, 1, &red(5)));
+        assert!(allows(&table(vec![vec![red(7)], vec![red(1)]]), 1, &red(7)));
+    }
+
+    #[test]
+    fn scripts_out_of_fuel_do_nothing() {
+        let mut room = scripted("spin", vec![vec![red(1)], vec![red(2)], vec![red(3)]]);
+
+        assert!(allows(&room, 1, &red(1)));
+        after_play(&mut room, 1, &red(1));
+        assert_eq!(room.current().id, 1);
+    }
+
+    #[test]
+    fn scripts_over_the_memory_limit_do_nothing() {
+        let hands = vec![vec![red(1)], vec![red(2)]];
+
+        assert!(allows(&scripted("big", hands.clone()), 1, &red(1)));
+        assert!(!allows(&scripted("fits", hands), 1, &red(1)));
+    }
+
+    #[test]
+    fn a_hook_has_a_limited_number_of_effects() {
+        let mut room = scripted("greedy", vec![vec![red(1)], vec![red(2)]]);
+
+        after_play(&mut room, 1, &red(1));
+        assert_eq!(room.scores[&1], MAX_EFFECTS as i32);
+    }
+
+    #[test]
+    fn scripts_can_skip_reverse_and_make_players_draw() {
+        let hands = vec![vec![red(1)], vec![red(2)], vec![red(3)]];
+
+        let mut room = scripted("skip", hands.clone());
+        after_play(&mut room, 1, &red(1));
+        assert_eq!(room.current().id, 3);
+
+        let mut room = scripted("reverse", hands.clone());
+        after_play(&mut room, 1, &red(1));
+        assert!(room.direction);
+
+        let mut room = scripted("draw", hands.clone());
+        after_play(&mut room, 1, &red(1));
+        assert_eq!(room.player(3).hand.len(), 3);
+        assert_eq!(room.drawn.len(), 1);
+
+        let mut room = scripted("skip_on_draw", hands);
+        on_draw(&mut room, 1, &red(9));
+        assert_eq!(room.current().id, 3);
+    }
+
+    #[test]
+    fn the_round_end_hook_gets_the_winner_and_only_scores() {
+        let mut room = scripted("winner", vec![vec![red(1)], vec![red(2)]]);
+
+        // The script scores the winner's id for player 1, and -1 for nobody.
+        on_round_end(&mut room, Some(2));
+        assert_eq!(room.scores[&1], 2);
+        assert_eq!(room.current().id, 1);
+
+        on_round_end(&mut room, None);
+        assert_eq!(room.scores[&1], 1);
+    }
+}
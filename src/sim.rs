@@ -0,0 +1,223 @@
+use crate::card::all;
+use crate::engine::{Engine, RuleError};
+use crate::room::Room;
+use crate::strategy::{GameView, Strategy};
+use crate::user::User;
+
+const MAX_MOVES: usize = 10000;
+const Z: f64 = 1.96;
+
+pub struct Entrant {
+    pub name: String,
+    pub strategy: Box<dyn Strategy>,
+}
+
+pub struct Outcome {
+    pub winner: usize,
+    pub moves: usize,
+}
+
+pub struct Standing {
+    pub name: String,
+    pub wins: usize,
+    pub win_rate: f64,
+    pub interval: (f64, f64),
+}
+
+pub struct Report {
+    pub games: usize,
+    pub unfinished: usize,
+    pub average_moves: f64,
+    pub moves_interval: (f64, f64),
+    pub standings: Vec<Standing>,
+}
+
+// Seats the entrants in the given order and plays until someone empties their
+// hand. Returns None if nobody has won after MAX_MOVES moves, and an error if
+// the entrants can't be dealt in.
+pub fn play_game(
+    entrants: &mut [Entrant],
+    order: &[usize],
+    seed: u64,
+) -> Result<Option<Outcome>, RuleError> {
+    let mut room = Room::new();
+    room.seed = seed;
+
+    for index in order {
+        let name = entrants[*index].name.clone();
+        room.players.push(User::new(name, *index as u32 + 1));
+    }
+
+    Engine::deal(&mut room, 1, &all())?;
+
+    for moves in 1..=MAX_MOVES {
+        let player_id = room.current().id;
+        let actions = Engine::legal_actions(&room, player_id);
+        let view = GameView::new(&room, player_id);
+        let choice = entrants[player_id as usize - 1]
+            .strategy
+            .choose(&view, &actions);
+
+        if Engine::apply(&mut room, player_id, choice).is_err() {
+            match actions.first() {
+                Some(action) => Engine::apply(&mut room, player_id, action.clone())?,
+                None => return Ok(None),
+            };
+        }
+
+        if let Some(winner) = room.players.iter().find(|player| player.hand.is_empty()) {
+            return Ok(Some(Outcome {
+                winner: winner.id as usize - 1,
+                moves,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+// Plays `games` games, rotating the seats every game so that no entrant keeps
+// the advantage of playing first.
+pub fn run(entrants: &mut [Entrant], games: usize, seed: u64) -> Result<Report, RuleError> {
+    let mut wins = vec![0; entrants.len()];
+    let mut lengths = vec![];
+
+    for game in 0..games {
+        let order = seating(entrants.len(), game);
+
+        if let Some(outcome) = play_game(entrants, &order, seed.wrapping_add(game as u64))? {
+            wins[outcome.winner] += 1;
+            lengths.push(outcome.moves as f64);
+        }
+    }
+
+    let (average_moves, moves_interval) = mean_interval(&lengths);
+
+    Ok(Report {
+        games,
+        unfinished: games - lengths.len(),
+        average_moves,
+        moves_interval,
+        standings: entrants
+            .iter()
+            .zip(wins)
+            .map(|(entrant, wins)| Standing {
+                name: entrant.name.clone(),
+                wins,
+                win_rate: if games == 0 {
+                    0.0
+                } else {
+                    wins as f64 / games as f64
+                },
+                interval: wilson_interval(wins, games),
+            })
+            .collect(),
+    })
+}
+
+// Who sits where in the given game: everyone moves one seat up each game.
+fn seating(players: usize, game: usize) -> Vec<usize> {
+    (0..players).map(|seat| (seat + game) % players).collect()
+}
+
+pub fn wilson_interval(successes: usize, trials: usize) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let denominator = 1.0 + Z * Z / n;
+    let center = (p + Z * Z / (2.0 * n)) / denominator;
+    let margin = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+    (center - margin, center + margin)
+}
+
+pub fn mean_interval(values: &[f64]) -> (f64, (f64, f64)) {
+    if values.is_empty() {
+        return (0.0, (0.0, 0.0));
+    }
+
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+
+    if values.len() < 2 {
+        return (mean, (mean, mean));
+    }
+
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let margin = Z * (variance / n).sqrt();
+    (mean, (mean - margin, mean + margin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::HeuristicStrategy;
+
+    fn entrants(count: usize) -> Vec<Entrant> {
+        (0..count)
+            .map(|i| Entrant {
+                name: format!("{}", i + 1),
+                strategy: Box::new(HeuristicStrategy),
+            })
+            .collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.0001
+    }
+
+    #[test]
+    fn wilson_intervals_stay_within_zero_and_one() {
+        let (low, high) = wilson_interval(5, 10);
+        assert!(close(low, 0.2366) && close(high, 0.7634));
+
+        let (low, high) = wilson_interval(0, 10);
+        assert!(close(low, 0.0) && high > 0.0);
+
+        let (low, high) = wilson_interval(10, 10);
+        assert!(low < 1.0 && close(high, 1.0));
+
+        assert_eq!(wilson_interval(0, 0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn mean_intervals_use_the_sample_variance() {
+        let (mean, (low, high)) = mean_interval(&[2.0, 4.0, 6.0]);
+        assert!(close(mean, 4.0));
+        assert!(close(low, 4.0 - 2.2632) && close(high, 4.0 + 2.2632));
+
+        assert_eq!(mean_interval(&[3.0]), (3.0, (3.0, 3.0)));
+        assert_eq!(mean_interval(&[]), (0.0, (0.0, 0.0)));
+    }
+
+    #[test]
+    fn everyone_gets_every_seat() {
+        assert_eq!(seating(3, 0), vec![0, 1, 2]);
+        assert_eq!(seating(3, 1), vec![1, 2, 0]);
+        assert_eq!(seating(3, 2), vec![2, 0, 1]);
+        assert_eq!(seating(3, 3), seating(3, 0));
+    }
+
+    #[test]
+    fn games_are_played_to_the_end() {
+        let report = run(&mut entrants(3), 20, 7).unwrap();
+        let wins: usize = report.standings.iter().map(|standing| standing.wins).sum();
+
+        assert_eq!(report.games, 20);
+        assert_eq!(wins, report.games - report.unfinished);
+    }
+
+    #[test]
+    fn more_entrants_than_the_deck_allows_are_refused() {
+        assert_eq!(
+            run(&mut entrants(8), 1, 7).err(),
+            Some(RuleError::TooManyPlayers(7))
+        );
+        assert_eq!(
+            run(&mut entrants(1), 1, 7).err(),
+            Some(RuleError::NotEnoughPlayers)
+        );
+    }
+}
@@ -0,0 +1,491 @@
+use crate::card::Card;
+use crate::event::{now, replay_with, Event, EventKind};
+use crate::rating::RatingChange;
+use crate::room::Room;
+use crate::uno::Uno;
+use crate::user::User;
+use actix_web::{web, HttpRequest, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const PER_PAGE: usize = 20;
+const MAX_PER_PAGE: usize = 100;
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct CardsPlayed {
+    pub number: u32,
+    pub stop: u32,
+    pub reverse: u32,
+    pub plus2: u32,
+    pub change_color: u32,
+    pub plus4: u32,
+    #[serde(default)]
+    pub flip: u32,
+    #[serde(default)]
+    pub draw_five: u32,
+    #[serde(default)]
+    pub skip_everyone: u32,
+    #[serde(default)]
+    pub wild_draw_color: u32,
+    #[serde(default)]
+    pub discard_all: u32,
+    #[serde(default)]
+    pub plus6: u32,
+    #[serde(default)]
+    pub plus10: u32,
+    #[serde(default)]
+    pub reverse_plus4: u32,
+    #[serde(default)]
+    pub house: u32,
+}
+
+impl CardsPlayed {
+    fn count(&mut self, card: &Card) {
+        match card {
+            Card::Number(_, _) => self.number += 1,
+            Card::Stop(_) => self.stop += 1,
+            Card::Reverse(_) => self.reverse += 1,
+            Card::Plus2(_) => self.plus2 += 1,
+            Card::ChangeColor => self.change_color += 1,
+            Card::Plus4 => self.plus4 += 1,
+            Card::Flip(_) => self.flip += 1,
+            Card::DrawFive(_) => self.draw_five += 1,
+            Card::SkipEveryone(_) => self.skip_everyone += 1,
+            Card::WildDrawColor => self.wild_draw_color += 1,
+            Card::DiscardAll(_) => self.discard_all += 1,
+            Card::Plus6 => self.plus6 += 1,
+            Card::Plus10 => self.plus10 += 1,
+            Card::ReversePlus4 => self.reverse_plus4 += 1,
+            Card::Custom(_, _) => self.house += 1,
+        }
+    }
+
+    fn add(&mut self, other: &CardsPlayed) {
+        self.number += other.number;
+        self.stop += other.stop;
+        self.reverse += other.reverse;
+        self.plus2 += other.plus2;
+        self.change_color += other.change_color;
+        self.plus4 += other.plus4;
+        self.flip += other.flip;
+        self.draw_five += other.draw_five;
+        self.skip_everyone += other.skip_everyone;
+        self.wild_draw_color += other.wild_draw_color;
+        self.discard_all += other.discard_all;
+        self.plus6 += other.plus6;
+        self.plus10 += other.plus10;
+        self.reverse_plus4 += other.reverse_plus4;
+        self.house += other.house;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlayerResult {
+    pub player_id: u32,
+    pub name: String,
+    pub placement: u32,
+    pub cards_played: CardsPlayed,
+    pub penalties: u32,
+    pub penalty_cards: u32,
+    pub turns: u32,
+    pub average_hand_size: f64,
+    #[serde(default)]
+    pub rating: Option<RatingChange>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MatchRecord {
+    pub room_id: u32,
+    pub ended_at: u64,
+    pub players: Vec<PlayerResult>,
+}
+
+impl MatchRecord {
+    pub fn result(&self, player_id: u32) -> Option<&PlayerResult> {
+        self.players
+            .iter()
+            .find(|player| player.player_id == player_id)
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Stats {
+    pub player_id: u32,
+    pub games: u32,
+    pub wins: u32,
+    // placements[0] is how many times the player finished first, and so on.
+    pub placements: Vec<u32>,
+    pub cards_played: CardsPlayed,
+    pub penalties: u32,
+    pub penalty_cards: u32,
+    pub average_hand_size: f64,
+    pub rating: f64,
+}
+
+// Works the results out of the event log of a finished round. Placements
+// follow the order in which hands were emptied, as the room noted it;
+// whoever still holds cards at the end comes last. The hand size is taken from
+// the replayed room every time a turn ends, before the move that ends it.
+pub fn record(room_id: u32, room: &Room, events: &[Event]) -> Option<MatchRecord> {
+    if !events
+        .iter()
+        .any(|event| matches!(event.kind, EventKind::Deal { .. }))
+    {
+        return None;
+    }
+
+    let mut hand_total: HashMap<u32, usize> = HashMap::new();
+    let mut results: Vec<PlayerResult> = room
+        .players
+        .iter()
+        .map(|player| PlayerResult {
+            player_id: player.id,
+            name: player.name.clone(),
+            placement: 0,
+            cards_played: CardsPlayed::default(),
+            penalties: 0,
+            penalty_cards: 0,
+            turns: 0,
+            average_hand_size: 0.0,
+            rating: None,
+        })
+        .collect();
+
+    replay_with(events, |before, event| {
+        let player_id = event.player_id;
+        let result = match results
+            .iter_mut()
+            .find(|result| result.player_id == player_id)
+        {
+            Some(result) => result,
+            None => return,
+        };
+
+        match &event.kind {
+            EventKind::Play { card } => result.cards_played.count(&card.1),
+            EventKind::Penalty { cards } => {
+                result.penalties += 1;
+                result.penalty_cards += cards.len() as u32;
+            }
+            EventKind::Pass => {}
+            _ => return,
+        }
+
+        result.turns += 1;
+        *hand_total.entry(player_id).or_default() += before
+            .players
+            .iter()
+            .find(|player| player.id == player_id)
+            .map(|player| player.hand.len())
+            .unwrap_or(0);
+    });
+
+    // The rest are ranked by what they really hold. Players knocked out by
+    // the mercy rule come last, the first one out at the bottom.
+    let mut order = room.finished.clone();
+    let mut rest: Vec<&User> = room
+        .players
+        .iter()
+        .filter(|player| !order.contains(&player.id) && !room.eliminated.contains(&player.id))
+        .collect();
+    rest.sort_by_key(|player| player.hand.len());
+    order.extend(rest.into_iter().map(|player| player.id));
+    order.extend(room.eliminated.iter().rev());
+
+    // Partners share their team's placement: the team of whoever went out
+    // first wins, the other one comes second.
+    let team = |player_id: u32| {
+        room.players
+            .iter()
+            .find(|player| player.id == player_id)
+            .and_then(|player| player.team)
+    };
+    let winners = order.first().and_then(|id| team(*id));
+
+    for result in results.iter_mut() {
+        result.placement = match (&room.teams, winners) {
+            (Some(_), Some(winners)) => {
+                if team(result.player_id) == Some(winners) {
+                    1
+                } else {
+                    2
+                }
+            }
+            _ => order
+                .iter()
+                .position(|id| *id == result.player_id)
+                .map(|place| place as u32 + 1)
+                .unwrap_or_default(),
+        };
+
+        if result.turns > 0 {
+            let total = hand_total.get(&result.player_id).cloned().unwrap_or(0);
+            result.average_hand_size = total as f64 / result.turns as f64;
+        }
+    }
+
+    results.sort_by_key(|result| result.placement);
+
+    Some(MatchRecord {
+        room_id,
+        ended_at: now(),
+        players: results,
+    })
+}
+
+pub fn stats(player_id: u32, matches: &[MatchRecord]) -> Stats {
+    let mut stats = Stats {
+        player_id,
+        ..Stats::default()
+    };
+    let mut turns = 0;
+    let mut hand_total = 0.0;
+
+    for result in matches.iter().filter_map(|record| record.result(player_id)) {
+        let place = result.placement as usize;
+
+        stats.games += 1;
+        if place == 1 {
+            stats.wins += 1;
+        }
+        if place > 0 {
+            if stats.placements.len() < place {
+                stats.placements.resize(place, 0);
+            }
+            stats.placements[place - 1] += 1;
+        }
+        stats.cards_played.add(&result.cards_played);
+        stats.penalties += result.penalties;
+        stats.penalty_cards += result.penalty_cards;
+        turns += result.turns;
+        hand_total += result.average_hand_size * result.turns as f64;
+    }
+
+    if turns > 0 {
+        stats.average_hand_size = hand_total / turns as f64;
+    }
+
+    stats
+}
+
+fn user_id(req: &HttpRequest) -> u32 {
+    req.match_info()
+        .get("user_id")
+        .unwrap()
+        .parse::<u32>()
+        .unwrap()
+}
+
+pub async fn user_stats(data: web::Data<Mutex<Uno>>, req: HttpRequest) -> impl Responder {
+    let context = data.lock().unwrap();
+    let player_id = user_id(&req);
+
+    if !context.users.contains_key(&player_id) {
+        return Err(actix_web::error::ErrorNotFound("User does not exist"));
+    }
+
+    let mut stats = stats(player_id, &context.matches);
+    stats.rating = context.ratings.get(player_id);
+    Ok(web::Json(stats))
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct History {
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+    pub matches: Vec<MatchRecord>,
+}
+
+// Pages start at 1.
+pub fn pages(page: Option<usize>, per_page: Option<usize>) -> (usize, usize) {
+    (
+        page.unwrap_or(1).max(1),
+        per_page.unwrap_or(PER_PAGE).clamp(1, MAX_PER_PAGE),
+    )
+}
+
+// Newest first.
+pub async fn history(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    let context = data.lock().unwrap();
+    let player_id = user_id(&req);
+
+    if !context.users.contains_key(&player_id) {
+        return Err(actix_web::error::ErrorNotFound("User does not exist"));
+    }
+
+    let (page, per_page) = pages(query.page, query.per_page);
+    let played: Vec<&MatchRecord> = context
+        .matches
+        .iter()
+        .rev()
+        .filter(|record| record.result(player_id).is_some())
+        .collect();
+
+    Ok(web::Json(History {
+        page,
+        per_page,
+        total: played.len(),
+        matches: played
+            .into_iter()
+            .skip(page.saturating_sub(1).saturating_mul(per_page))
+            .take(per_page)
+            .cloned()
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::card::CardColor;
+    use crate::engine::tests::{play, red, table};
+    use crate::engine::{Action, Engine};
+    use crate::room::RuleSet;
+
+    fn room(hands: &[usize]) -> Room {
+        let mut room = Room::new();
+
+        for (seat, cards) in hands.iter().enumerate() {
+            let id = seat as u32 + 1;
+            let mut user = User::new(format!("p{}", id), id);
+            user.hand = vec![(0, Card::Number(1, CardColor::Red)); *cards];
+            room.players.push(user);
+        }
+
+        room.active = true;
+        room
+    }
+
+    pub(crate) fn result(player_id: u32, placement: u32) -> PlayerResult {
+        PlayerResult {
+            player_id,
+            name: format!("p{}", player_id),
+            placement,
+            cards_played: CardsPlayed::default(),
+            penalties: 0,
+            penalty_cards: 0,
+            turns: 0,
+            average_hand_size: 0.0,
+            rating: None,
+        }
+    }
+
+    // The log of a room the given players joined, up to the deal.
+    fn dealt(players: u32) -> Vec<Event> {
+        let mut events: Vec<Event> = (1..=players)
+            .map(|id| {
+                let name = format!("p{}", id);
+                Event::new(id, EventKind::Join { name, bot: None })
+            })
+            .collect();
+        events.push(Event::new(1, EventKind::Deal { deck: vec![] }));
+        events
+    }
+
+    fn placements(record: &MatchRecord) -> Vec<(u32, u32)> {
+        let mut placements: Vec<(u32, u32)> = record
+            .players
+            .iter()
+            .map(|result| (result.player_id, result.placement))
+            .collect();
+        placements.sort();
+        placements
+    }
+
+    #[test]
+    fn a_round_without_a_deal_is_not_recorded() {
+        assert!(record(1, &room(&[0, 3]), &[]).is_none());
+    }
+
+    #[test]
+    fn placements_follow_the_order_hands_were_emptied() {
+        let mut room = room(&[0, 0, 2, 5]);
+        room.finished = vec![2, 1];
+
+        let record = record(7, &room, &dealt(4)).unwrap();
+        assert_eq!(record.room_id, 7);
+        assert_eq!(placements(&record), vec![(1, 2), (2, 1), (3, 3), (4, 4)]);
+        assert_eq!(record.players[0].player_id, 2);
+    }
+
+    #[test]
+    fn knocked_out_players_come_last() {
+        let mut room = room(&[0, 3, 0, 0]);
+        room.finished = vec![1];
+        room.eliminated = vec![3, 4];
+
+        let record = record(1, &room, &dealt(4)).unwrap();
+        assert_eq!(placements(&record), vec![(1, 1), (2, 2), (3, 4), (4, 3)]);
+    }
+
+    #[test]
+    fn hand_sizes_are_read_from_the_room() {
+        let mut events = dealt(2);
+
+        // Known hands, set the way an admin import would.
+        let mut room = table(vec![
+            vec![
+                Card::DiscardAll(CardColor::Red),
+                red(1),
+                red(2),
+                Card::Number(3, CardColor::Blue),
+            ],
+            vec![red(7), red(8)],
+        ]);
+        room.rules = RuleSet::NoMercy;
+        let snapshot = Box::new(room.clone());
+        events.push(Event::new(0, EventKind::Import { room: snapshot }));
+
+        let card = Card::DiscardAll(CardColor::Red);
+        events.extend(play(&mut room, 1, &card, None));
+        events.extend(play(&mut room, 2, &red(7), None));
+        events.extend(Engine::apply(&mut room, 1, Action::Draw).unwrap());
+        events.extend(Engine::apply(&mut room, 1, Action::Pass).unwrap());
+
+        let record = record(1, &room, &events).unwrap();
+        let player = |player_id| record.result(player_id).unwrap();
+
+        // Four cards before the Discard All, then the one left and the eight
+        // drawn until a card fit.
+        assert_eq!(player(1).turns, 2);
+        assert_eq!(player(1).average_hand_size, 6.5);
+        assert_eq!(player(2).turns, 1);
+        assert_eq!(player(2).average_hand_size, 2.0);
+    }
+
+    #[test]
+    fn stats_weigh_hand_sizes_by_turns() {
+        let mut first = result(1, 1);
+        first.turns = 1;
+        first.average_hand_size = 2.0;
+        let mut second = result(1, 2);
+        second.turns = 3;
+        second.average_hand_size = 6.0;
+        let matches: Vec<MatchRecord> = vec![first, second]
+            .into_iter()
+            .map(|result| MatchRecord {
+                room_id: 1,
+                ended_at: 0,
+                players: vec![result],
+            })
+            .collect();
+
+        let stats = stats(1, &matches);
+        assert_eq!(stats.games, 2);
+        assert_eq!(stats.wins, 1);
+        assert_eq!(stats.placements, vec![1, 1]);
+        assert_eq!(stats.average_hand_size, 5.0);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::event::Event;
+use crate::room::Room;
+use crate::stats::MatchRecord;
+use crate::tournament::Tournament;
+use crate::user::User;
+use rusqlite::{params, Connection};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+};
+
+#[derive(Debug)]
+pub struct StorageError(pub String);
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> StorageError {
+        StorageError(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(error: serde_json::Error) -> StorageError {
+        StorageError(error.to_string())
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+pub trait Storage: Send + Debug {
+    fn save_user(&mut self, user: &User) -> StorageResult<()>;
+    fn users(&self) -> StorageResult<Vec<User>>;
+    fn save_room(&mut self, room_id: u32, room: &Room) -> StorageResult<()>;
+    fn rooms(&self) -> StorageResult<Vec<(u32, Room)>>;
+    fn append_event(&mut self, room_id: u32, event: &Event) -> StorageResult<()>;
+    fn events(&self, room_id: u32) -> StorageResult<Vec<Event>>;
+    fn save_match(&mut self, record: &MatchRecord) -> StorageResult<()>;
+    fn matches(&self) -> StorageResult<Vec<MatchRecord>>;
+    fn save_tournament(&mut self, tournament_id: u32, tournament: &Tournament)
+        -> StorageResult<()>;
+    fn tournaments(&self) -> StorageResult<Vec<(u32, Tournament)>>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    users: HashMap<u32, User>,
+    rooms: HashMap<u32, Room>,
+    events: HashMap<u32, Vec<Event>>,
+    matches: Vec<MatchRecord>,
+    tournaments: HashMap<u32, Tournament>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn save_user(&mut self, user: &User) -> StorageResult<()> {
+        self.users.insert(user.id, user.clone());
+        Ok(())
+    }
+
+    fn users(&self) -> StorageResult<Vec<User>> {
+        Ok(self.users.values().cloned().collect())
+    }
+
+    fn save_room(&mut self, room_id: u32, room: &Room) -> StorageResult<()> {
+        self.rooms.insert(room_id, room.clone());
+        Ok(())
+    }
+
+    fn rooms(&self) -> StorageResult<Vec<(u32, Room)>> {
+        Ok(self
+            .rooms
+            .iter()
+            .map(|(id, room)| (*id, room.clone()))
+            .collect())
+    }
+
+    fn append_event(&mut self, room_id: u32, event: &Event) -> StorageResult<()> {
+        self.events.entry(room_id).or_default().push(event.clone());
+        Ok(())
+    }
+
+    fn events(&self, room_id: u32) -> StorageResult<Vec<Event>> {
+        Ok(self.events.get(&room_id).cloned().unwrap_or_default())
+    }
+
+    fn save_match(&mut self, record: &MatchRecord) -> StorageResult<()> {
+        self.matches.push(record.clone());
+        Ok(())
+    }
+
+    fn matches(&self) -> StorageResult<Vec<MatchRecord>> {
+        Ok(self.matches.clone())
+    }
+
+    fn save_tournament(
+        &mut self,
+        tournament_id: u32,
+        tournament: &Tournament,
+    ) -> StorageResult<()> {
+        self.tournaments.insert(tournament_id, tournament.clone());
+        Ok(())
+    }
+
+    fn tournaments(&self) -> StorageResult<Vec<(u32, Tournament)>> {
+        Ok(self
+            .tournaments
+            .iter()
+            .map(|(id, tournament)| (*id, tournament.clone()))
+            .collect())
+    }
+}
+
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> StorageResult<SqliteStorage> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS users (
+                id INTEGER PRIMARY KEY,
+                data TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS rooms (
+                id INTEGER PRIMARY KEY,
+                data TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS events (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                room_id INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_room ON events (room_id, seq);
+            CREATE TABLE IF NOT EXISTS matches (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                room_id INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS tournaments (
+                id INTEGER PRIMARY KEY,
+                data TEXT NOT NULL
+            );",
+        )?;
+        Ok(SqliteStorage { connection })
+    }
+}
+
+impl Debug for SqliteStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SqliteStorage")
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn save_user(&mut self, user: &User) -> StorageResult<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO users (id, data) VALUES (?1, ?2)",
+            params![user.id, serde_json::to_string(user)?],
+        )?;
+        Ok(())
+    }
+
+    fn users(&self) -> StorageResult<Vec<User>> {
+        let mut statement = self.connection.prepare("SELECT data FROM users")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut users = vec![];
+
+        for row in rows {
+            users.push(serde_json::from_str(&row?)?);
+        }
+
+        Ok(users)
+    }
+
+    fn save_room(&mut self, room_id: u32, room: &Room) -> StorageResult<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO rooms (id, data) VALUES (?1, ?2)",
+            params![room_id, serde_json::to_string(room)?],
+        )?;
+        Ok(())
+    }
+
+    fn rooms(&self) -> StorageResult<Vec<(u32, Room)>> {
+        let mut statement = self.connection.prepare("SELECT id, data FROM rooms")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut rooms = vec![];
+
+        for row in rows {
+            let (id, data) = row?;
+            rooms.push((id, serde_json::from_str(&data)?));
+        }
+
+        Ok(rooms)
+    }
+
+    fn append_event(&mut self, room_id: u32, event: &Event) -> StorageResult<()> {
+        self.connection.execute(
+            "INSERT INTO events (room_id, data) VALUES (?1, ?2)",
+            params![room_id, serde_json::to_string(event)?],
+        )?;
+        Ok(())
+    }
+
+    fn events(&self, room_id: u32) -> StorageResult<Vec<Event>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT data FROM events WHERE room_id = ?1 ORDER BY seq")?;
+        let rows = statement.query_map([room_id], |row| row.get::<_, String>(0))?;
+        let mut events = vec![];
+
+        for row in rows {
+            events.push(serde_json::from_str(&row?)?);
+        }
+
+        Ok(events)
+    }
+
+    fn save_match(&mut self, record: &MatchRecord) -> StorageResult<()> {
+        self.connection.execute(
+            "INSERT INTO matches (room_id, data) VALUES (?1, ?2)",
+            params![record.room_id, serde_json::to_string(record)?],
+        )?;
+        Ok(())
+    }
+
+    fn matches(&self) -> StorageResult<Vec<MatchRecord>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT data FROM matches ORDER BY seq")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut matches = vec![];
+
+        for row in rows {
+            matches.push(serde_json::from_str(&row?)?);
+        }
+
+        Ok(matches)
+    }
+
+    fn save_tournament(
+        &mut self,
+        tournament_id: u32,
+        tournament: &Tournament,
+    ) -> StorageResult<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO tournaments (id, data) VALUES (?1, ?2)",
+            params![tournament_id, serde_json::to_string(tournament)?],
+        )?;
+        Ok(())
+    }
+
+    fn tournaments(&self) -> StorageResult<Vec<(u32, Tournament)>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, data FROM tournaments")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut tournaments = vec![];
+
+        for row in rows {
+            let (id, data) = row?;
+            tournaments.push((id, serde_json::from_str(&data)?));
+        }
+
+        Ok(tournaments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::all;
+    use crate::event::EventKind;
+
+    fn json<T: serde::Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    fn dealt() -> Room {
+        let mut room = Room::new();
+        room.seed = 7;
+        room.seat(User::new("ana".to_string(), 1));
+        room.seat(User::new("bo".to_string(), 2));
+        room.deal(&all());
+        room
+    }
+
+    fn round_trip(storage: &mut dyn Storage) {
+        let user = User::new("ana".to_string(), 1);
+        let room = dealt();
+
+        storage.save_user(&user).unwrap();
+        storage.save_user(&user).unwrap();
+        storage.save_room(3, &Room::new()).unwrap();
+        storage.save_room(3, &room).unwrap();
+        storage
+            .append_event(3, &Event::new(0, EventKind::Seed { seed: 7 }))
+            .unwrap();
+        storage
+            .append_event(3, &Event::new(1, EventKind::Pass))
+            .unwrap();
+        storage
+            .append_event(4, &Event::new(2, EventKind::Leave))
+            .unwrap();
+
+        let users = storage.users().unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(json(&users[0]), json(&user));
+
+        let rooms = storage.rooms().unwrap();
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].0, 3);
+        assert_eq!(json(&rooms[0].1), json(&room));
+
+        let events = storage.events(3).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0].kind, EventKind::Seed { seed: 7 }));
+        assert!(matches!(events[1].kind, EventKind::Pass));
+        assert_eq!(events[1].player_id, 1);
+        assert!(storage.events(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_storage_round_trips() {
+        round_trip(&mut MemoryStorage::new());
+    }
+
+    #[test]
+    fn sqlite_storage_round_trips() {
+        round_trip(&mut SqliteStorage::open(":memory:").unwrap());
+    }
+
+    #[test]
+    fn sqlite_storage_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("uno-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let room = dealt();
+
+        SqliteStorage::open(path)
+            .unwrap()
+            .save_room(1, &room)
+            .unwrap();
+        let rooms = SqliteStorage::open(path).unwrap().rooms().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(json(&rooms[0].1), json(&room));
+    }
+}
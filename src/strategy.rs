@@ -0,0 +1,333 @@
+use crate::card::{Card, CardColor, GameState};
+use crate::engine::Action;
+use crate::room::{Room, RuleSet, Teams};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+pub const COLORS: [CardColor; 4] = [
+    CardColor::Red,
+    CardColor::Green,
+    CardColor::Blue,
+    CardColor::Yellow,
+];
+
+pub const DARK_COLORS: [CardColor; 4] = [
+    CardColor::Pink,
+    CardColor::Teal,
+    CardColor::Orange,
+    CardColor::Purple,
+];
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum BotKind {
+    Random,
+    Heuristic,
+    External(String),
+}
+
+impl BotKind {
+    pub fn is_available(&self) -> bool {
+        match self {
+            BotKind::External(name) => external_command(name).is_some(),
+            _ => true,
+        }
+    }
+
+    pub fn strategy(&self) -> Box<dyn Strategy> {
+        match self {
+            BotKind::Random => Box::new(RandomStrategy),
+            BotKind::Heuristic => Box::new(HeuristicStrategy),
+            BotKind::External(name) => {
+                let spawned = external_command(name)
+                    .ok_or_else(|| format!("Unknown external bot {}", name))
+                    .and_then(|command| {
+                        ExternalStrategy::spawn(&command).map_err(|e| e.to_string())
+                    });
+
+                match spawned {
+                    Ok(strategy) => Box::new(strategy),
+                    Err(error) => {
+                        eprintln!("Could not start {}: {}", name, error);
+                        Box::new(RandomStrategy)
+                    }
+                }
+            }
+        }
+    }
+}
+
+// External bots are configured by whoever runs the server, never by players:
+// UNO_EXTERNAL_BOTS="name=command args;other=command args"
+fn external_command(name: &str) -> Option<String> {
+    let bots = env::var("UNO_EXTERNAL_BOTS").ok()?;
+
+    bots.split(';').find_map(|entry| {
+        let (key, command) = entry.split_once('=')?;
+        if key.trim() == name {
+            Some(command.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Seat {
+    pub id: u32,
+    pub name: String,
+    pub cards: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GameView {
+    pub player_id: u32,
+    pub hand: Vec<(u8, Card)>,
+    pub drawed: bool,
+    pub seats: Vec<Seat>,
+    pub top: (u8, Card),
+    pub color: CardColor,
+    pub state: Option<GameState>,
+    pub chain_count: u8,
+    pub direction: bool,
+    pub deck_size: usize,
+    pub rules: RuleSet,
+    pub dark: bool,
+    pub pending: u8,
+    pub eliminated: Vec<u32>,
+    pub teams: Option<Teams>,
+}
+
+impl GameView {
+    pub fn new(room: &Room, player_id: u32) -> GameView {
+        let player = room
+            .players
+            .iter()
+            .find(|player| player.id == player_id)
+            .unwrap();
+
+        GameView {
+            player_id,
+            hand: player.hand.clone(),
+            drawed: player.drawed,
+            seats: room
+                .players
+                .iter()
+                .map(|player| Seat {
+                    id: player.id,
+                    name: player.name.clone(),
+                    cards: player.hand.len(),
+                })
+                .collect(),
+            top: room.top(),
+            color: room.color.clone(),
+            state: room.state.clone(),
+            chain_count: room.chain_count,
+            direction: room.direction,
+            deck_size: room.deck.len(),
+            rules: room.rules.clone(),
+            dark: room.dark,
+            pending: room.pending,
+            eliminated: room.eliminated.clone(),
+            teams: room.teams.clone(),
+        }
+    }
+}
+
+pub trait Strategy: Send {
+    fn choose(&mut self, view: &GameView, actions: &[Action]) -> Action;
+}
+
+pub struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn choose(&mut self, _view: &GameView, actions: &[Action]) -> Action {
+        let plays: Vec<&Action> = actions
+            .iter()
+            .filter(|play| matches!(play, Action::Play { .. }))
+            .collect();
+
+        match plays.choose(&mut thread_rng()) {
+            Some(play) => (*play).clone(),
+            None => actions[0].clone(),
+        }
+    }
+}
+
+pub struct HeuristicStrategy;
+
+impl HeuristicStrategy {
+    fn points(card: &Card) -> u8 {
+        match card {
+            Card::Number(number, _) => *number,
+            Card::Stop(_) | Card::Reverse(_) | Card::Plus2(_) | Card::Flip(_) => 20,
+            Card::DrawFive(_) | Card::SkipEveryone(_) | Card::DiscardAll(_) => 30,
+            Card::Custom(_, Some(_)) => 20,
+            Card::Custom(_, None) => 50,
+            Card::ChangeColor | Card::Plus4 | Card::ReversePlus4 => 50,
+            Card::WildDrawColor | Card::Plus6 | Card::Plus10 => 60,
+        }
+    }
+
+    fn held(view: &GameView, card_id: u8, color: &Option<CardColor>) -> usize {
+        view.hand
+            .iter()
+            .filter(|(id, card)| *id != card_id && card.clone().get_color() == *color)
+            .count()
+    }
+}
+
+impl Strategy for HeuristicStrategy {
+    fn choose(&mut self, view: &GameView, actions: &[Action]) -> Action {
+        let card = |card_id: u8| {
+            view.hand
+                .iter()
+                .find(|(id, _)| *id == card_id)
+                .map(|(_, card)| card.clone())
+                .unwrap()
+        };
+
+        let best = actions
+            .iter()
+            .filter_map(|play| match play {
+                Action::Play { card_id, color } => Some((
+                    HeuristicStrategy::points(&card(*card_id)),
+                    HeuristicStrategy::held(view, *card_id, color),
+                    play,
+                )),
+                _ => None,
+            })
+            .max_by_key(|(points, held, _)| (*points, *held));
+
+        match best {
+            Some((_, _, play)) => play.clone(),
+            None => actions[0].clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Turn<'a> {
+    pub view: &'a GameView,
+    pub moves: &'a [Action],
+}
+
+// Speaks one JSON line per turn: the server writes `{"view": ..., "moves": ...}`
+// and the process answers with one of the moves. Anything else, or no answer
+// in time, falls back to the first legal move.
+pub struct ExternalStrategy {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    timeout: Duration,
+}
+
+impl ExternalStrategy {
+    pub fn spawn(command: &str) -> std::io::Result<ExternalStrategy> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().unwrap_or_default();
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(ExternalStrategy {
+            child,
+            stdin,
+            lines,
+            timeout: Duration::from_secs(5),
+        })
+    }
+
+    fn ask(&mut self, view: &GameView, actions: &[Action]) -> Result<Action, String> {
+        let turn = serde_json::to_string(&Turn {
+            view,
+            moves: actions,
+        })
+        .map_err(|e| e.to_string())?;
+
+        // A reply that came in after an earlier timeout answers an old turn.
+        while self.lines.try_recv().is_ok() {}
+
+        writeln!(self.stdin, "{}", turn).map_err(|e| e.to_string())?;
+        self.stdin.flush().map_err(|e| e.to_string())?;
+
+        let line = self
+            .lines
+            .recv_timeout(self.timeout)
+            .map_err(|e| e.to_string())?;
+        let choice: Action = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+
+        if actions.contains(&choice) {
+            Ok(choice)
+        } else {
+            Err(format!("Illegal move {}", line))
+        }
+    }
+}
+
+impl Strategy for ExternalStrategy {
+    fn choose(&mut self, view: &GameView, actions: &[Action]) -> Action {
+        match self.ask(view, actions) {
+            Ok(choice) => choice,
+            Err(error) => {
+                eprintln!("External bot failed: {}", error);
+                actions[0].clone()
+            }
+        }
+    }
+}
+
+impl Drop for ExternalStrategy {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::tests::{red, table};
+
+    #[test]
+    fn a_late_answer_is_not_taken_for_the_next_turn() {
+        let room = table(vec![vec![red(1)], vec![red(2)]]);
+        let view = GameView::new(&room, 1);
+        let actions = [Action::Draw, Action::Pass];
+        // Answers the first turn too late, then the second one right away.
+        let script = std::env::temp_dir().join("uno-late-bot.sh");
+        std::fs::write(
+            &script,
+            "read a; sleep 0.3; echo '\"Draw\"'\nread b; echo '\"Pass\"'\n",
+        )
+        .unwrap();
+        let mut bot = ExternalStrategy::spawn(&format!("sh {}", script.display())).unwrap();
+
+        bot.timeout = Duration::from_millis(100);
+        assert!(bot.ask(&view, &actions).is_err());
+        thread::sleep(Duration::from_millis(500));
+
+        bot.timeout = Duration::from_secs(5);
+        assert_eq!(bot.ask(&view, &actions), Ok(Action::Pass));
+    }
+}
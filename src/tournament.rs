@@ -0,0 +1,626 @@
+use crate::event::{Event, EventKind};
+use crate::room::RuleSet;
+use crate::stats::MatchRecord;
+use crate::uno::{get_user, Uno};
+use actix_session::Session;
+use actix_web::{web, HttpRequest, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum TournamentState {
+    Registration,
+    Running,
+    Finished,
+}
+
+// A table without a room is a bye: its only player goes straight through.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Table {
+    pub room_id: Option<u32>,
+    pub players: Vec<u32>,
+    pub no_shows: Vec<u32>,
+    pub advanced: Vec<u32>,
+    pub done: bool,
+}
+
+impl Table {
+    // Only the first round played at a table counts.
+    fn plays_in(&self, room_id: u32) -> bool {
+        !self.done && self.room_id == Some(room_id)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Tournament {
+    pub name: String,
+    pub host: u32,
+    pub table_size: usize,
+    pub advance: usize,
+    #[serde(default)]
+    pub rules: RuleSet,
+    pub state: TournamentState,
+    pub entrants: Vec<u32>,
+    pub rounds: Vec<Vec<Table>>,
+    pub winner: Option<u32>,
+}
+
+impl Tournament {
+    pub fn new(name: String, host: u32, table_size: usize, advance: usize) -> Tournament {
+        Tournament {
+            name,
+            host,
+            table_size,
+            advance,
+            rules: RuleSet::Classic,
+            state: TournamentState::Registration,
+            entrants: vec![],
+            rounds: vec![],
+            winner: None,
+        }
+    }
+
+    // Uses the fewest tables that fit everybody and deals the players out in
+    // a snake, so with players sorted by rating every table gets a similar
+    // mix and sizes differ by one at most.
+    pub fn draw_tables(&self, players: &[u32]) -> Vec<Table> {
+        let count = players.len().div_ceil(self.table_size);
+        let mut tables = vec![Table::default(); count];
+
+        for (index, player_id) in players.iter().enumerate() {
+            let lap = index / count;
+            let seat = index % count;
+            let table = if lap.is_multiple_of(2) {
+                seat
+            } else {
+                count - 1 - seat
+            };
+            tables[table].players.push(*player_id);
+        }
+
+        tables
+    }
+
+    // Every table knocks out at least one player, so the field always shrinks.
+    pub fn places(&self, table: &Table) -> usize {
+        self.advance
+            .min(table.players.len().saturating_sub(1))
+            .max(1)
+    }
+
+    pub fn current(&mut self) -> Option<&mut Vec<Table>> {
+        self.rounds.last_mut()
+    }
+
+    pub fn round_done(&self) -> bool {
+        self.rounds
+            .last()
+            .is_some_and(|tables| tables.iter().all(|table| table.done))
+    }
+
+    pub fn advanced(&self) -> Vec<u32> {
+        self.rounds
+            .last()
+            .map(|tables| {
+                tables
+                    .iter()
+                    .flat_map(|table| table.advanced.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+// Seats the next round, or names the winner once a single player is left.
+// Players are ordered by rating before drawing the tables.
+fn seat_round(context: &mut Uno, tournament_id: u32, mut players: Vec<u32>) {
+    loop {
+        let tournament = match context.tournaments.get(&tournament_id) {
+            Some(tournament) => tournament,
+            None => return,
+        };
+
+        if players.len() <= 1 {
+            let tournament = context.tournaments.get_mut(&tournament_id).unwrap();
+            tournament.winner = players.first().cloned();
+            tournament.state = TournamentState::Finished;
+            break;
+        }
+
+        players.sort_by(|a, b| {
+            let (a, b) = (context.ratings.get(*a), context.ratings.get(*b));
+            b.partial_cmp(&a).unwrap()
+        });
+
+        let rules = tournament.rules.clone();
+        let mut tables = tournament.draw_tables(&players);
+
+        for table in tables.iter_mut() {
+            if table.players.len() == 1 {
+                table.advanced = table.players.clone();
+                table.done = true;
+                continue;
+            }
+
+            let room_id = context.create_room(rules.clone());
+            for player_id in table.players.iter() {
+                if let Some(user) = context.users.get(player_id).cloned() {
+                    context.join(room_id, user);
+                }
+            }
+            table.room_id = Some(room_id);
+        }
+
+        let tournament = context.tournaments.get_mut(&tournament_id).unwrap();
+        tournament.rounds.push(tables);
+
+        if !tournament.round_done() {
+            break;
+        }
+
+        players = tournament.advanced();
+    }
+
+    context.save_tournament(tournament_id);
+}
+
+fn next_round_if_done(context: &mut Uno, tournament_id: u32) {
+    let tournament = &context.tournaments[&tournament_id];
+
+    if tournament.round_done() {
+        let players = tournament.advanced();
+        seat_round(context, tournament_id, players);
+    } else {
+        context.save_tournament(tournament_id);
+    }
+}
+
+// Called when a round ends in any room. The final table only crowns its
+// winner; other tables send their top finishers to the next round.
+pub fn on_round_end(context: &mut Uno, room_id: u32, record: &MatchRecord) {
+    let tournament_id = match context.tournaments.iter().find(|(_, tournament)| {
+        tournament.state == TournamentState::Running
+            && tournament
+                .rounds
+                .last()
+                .is_some_and(|tables| tables.iter().any(|table| table.plays_in(room_id)))
+    }) {
+        Some((id, _)) => *id,
+        None => return,
+    };
+
+    let tournament = context.tournaments.get_mut(&tournament_id).unwrap();
+    let final_table = tournament.rounds.last().unwrap().len() == 1;
+    let places = {
+        let table = tournament.rounds.last().unwrap();
+        let table = table.iter().find(|table| table.plays_in(room_id)).unwrap();
+        if final_table {
+            1
+        } else {
+            tournament.places(table)
+        }
+    };
+
+    let table = tournament
+        .current()
+        .unwrap()
+        .iter_mut()
+        .find(|table| table.plays_in(room_id))
+        .unwrap();
+
+    table.advanced = record
+        .players
+        .iter()
+        .map(|result| result.player_id)
+        .filter(|player_id| table.players.contains(player_id))
+        .take(places)
+        .collect();
+    table.done = true;
+
+    next_round_if_done(context, tournament_id);
+}
+
+fn tournament_id(req: &HttpRequest) -> u32 {
+    req.match_info()
+        .get("tournament_id")
+        .unwrap()
+        .parse::<u32>()
+        .unwrap()
+}
+
+#[derive(Deserialize)]
+pub struct NewTournamentReq {
+    pub username: String,
+    pub name: String,
+    pub table_size: usize,
+    pub advance: usize,
+    #[serde(default)]
+    pub rules: RuleSet,
+}
+
+#[derive(Serialize)]
+struct NewTournamentRes {
+    pub tournament_id: u32,
+}
+
+pub async fn create(
+    data: web::Data<Mutex<Uno>>,
+    session: Session,
+    form: web::Json<NewTournamentReq>,
+) -> impl Responder {
+    let max = form.rules.max_players();
+    if form.table_size < 2 || form.table_size > max {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "Tables are for 2 to {} players",
+            max
+        )));
+    }
+
+    if form.advance < 1 || form.advance >= form.table_size {
+        return Err(actix_web::error::ErrorBadRequest(
+            "At least one player per table must advance and one must not",
+        ));
+    }
+
+    let mut context = data.lock().unwrap();
+    let user = get_user(&mut context, &form.username, &session);
+    let mut tournament = Tournament::new(form.name.clone(), user.id, form.table_size, form.advance);
+    tournament.rules = form.rules.clone();
+
+    context.tournament_index += 1;
+    let tournament_id = context.tournament_index;
+    context.tournaments.insert(tournament_id, tournament);
+    context.save_tournament(tournament_id);
+
+    Ok(web::Json(NewTournamentRes { tournament_id }))
+}
+
+#[derive(Serialize)]
+struct Summary {
+    pub id: u32,
+    pub name: String,
+    pub state: TournamentState,
+    pub entrants: usize,
+    pub winner: Option<u32>,
+}
+
+pub async fn list(data: web::Data<Mutex<Uno>>) -> impl Responder {
+    let context = data.lock().unwrap();
+    let mut summaries: Vec<Summary> = context
+        .tournaments
+        .iter()
+        .map(|(id, tournament)| Summary {
+            id: *id,
+            name: tournament.name.clone(),
+            state: tournament.state.clone(),
+            entrants: tournament.entrants.len(),
+            winner: tournament.winner,
+        })
+        .collect();
+
+    summaries.sort_by_key(|summary| summary.id);
+    web::Json(summaries)
+}
+
+#[derive(Serialize)]
+struct Bracket {
+    pub id: u32,
+    #[serde(flatten)]
+    pub tournament: Tournament,
+    pub names: HashMap<u32, String>,
+}
+
+pub async fn bracket(data: web::Data<Mutex<Uno>>, req: HttpRequest) -> impl Responder {
+    let context = data.lock().unwrap();
+    let id = tournament_id(&req);
+
+    let tournament = match context.tournaments.get(&id) {
+        Some(tournament) => tournament,
+        None => return Err(actix_web::error::ErrorNotFound("Tournament does not exist")),
+    };
+
+    let names = tournament
+        .entrants
+        .iter()
+        .filter_map(|player_id| {
+            let user = context.users.get(player_id)?;
+            Some((*player_id, user.name.clone()))
+        })
+        .collect();
+
+    Ok(web::Json(Bracket {
+        id,
+        tournament: tournament.clone(),
+        names,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct JoinTournamentReq {
+    pub username: String,
+}
+
+pub async fn join(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<JoinTournamentReq>,
+) -> impl Responder {
+    let mut context = data.lock().unwrap();
+    let id = tournament_id(&req);
+    let user = get_user(&mut context, &form.username, &session);
+
+    let tournament = match context.tournaments.get_mut(&id) {
+        Some(tournament) => tournament,
+        None => return Err(actix_web::error::ErrorNotFound("Tournament does not exist")),
+    };
+
+    if tournament.state != TournamentState::Registration {
+        return Err(actix_web::error::ErrorBadRequest("Registration is closed"));
+    }
+
+    if !tournament.entrants.contains(&user.id) {
+        tournament.entrants.push(user.id);
+    }
+
+    context.save_tournament(id);
+    Ok("")
+}
+
+pub async fn start(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+) -> impl Responder {
+    let mut context = data.lock().unwrap();
+    let id = tournament_id(&req);
+    let player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+
+    let tournament = match context.tournaments.get_mut(&id) {
+        Some(tournament) => tournament,
+        None => return Err(actix_web::error::ErrorNotFound("Tournament does not exist")),
+    };
+
+    if tournament.host != player_id {
+        return Err(actix_web::error::ErrorUnauthorized(
+            "Only the host can start the tournament",
+        ));
+    }
+
+    if tournament.state != TournamentState::Registration {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Tournament already started",
+        ));
+    }
+
+    if tournament.entrants.len() < 2 {
+        return Err(actix_web::error::ErrorBadRequest("Not enough players"));
+    }
+
+    tournament.state = TournamentState::Running;
+    let entrants = tournament.entrants.clone();
+    seat_round(&mut context, id, entrants);
+    Ok("")
+}
+
+#[derive(Deserialize)]
+pub struct NoShowReq {
+    pub player_id: u32,
+}
+
+// The host takes a player who never turned up off their table before it
+// starts. A table left with a single player becomes a bye.
+pub async fn no_show(
+    data: web::Data<Mutex<Uno>>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Json<NoShowReq>,
+) -> impl Responder {
+    let mut context = data.lock().unwrap();
+    let id = tournament_id(&req);
+    let player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+
+    let tournament = match context.tournaments.get(&id) {
+        Some(tournament) => tournament,
+        None => return Err(actix_web::error::ErrorNotFound("Tournament does not exist")),
+    };
+
+    if tournament.host != player_id {
+        return Err(actix_web::error::ErrorUnauthorized(
+            "Only the host can report a no-show",
+        ));
+    }
+
+    if tournament.state != TournamentState::Running {
+        return Err(actix_web::error::ErrorBadRequest(
+            "The tournament is not running",
+        ));
+    }
+
+    let table = tournament
+        .rounds
+        .last()
+        .unwrap()
+        .iter()
+        .position(|table| !table.done && table.players.contains(&form.player_id));
+
+    let table = match table {
+        Some(table) => table,
+        None => return Err(actix_web::error::ErrorBadRequest("Player is not seated")),
+    };
+
+    let room_id = tournament.rounds.last().unwrap()[table].room_id;
+
+    if let Some(room_id) = room_id {
+        let room = context.rooms.get_mut(&room_id).unwrap();
+
+        if room.active {
+            return Err(actix_web::error::ErrorBadRequest(
+                "The table has already started",
+            ));
+        }
+
+        room.players.retain(|player| player.id != form.player_id);
+        context.save_room(room_id, vec![Event::new(form.player_id, EventKind::Leave)]);
+    }
+
+    let tournament = context.tournaments.get_mut(&id).unwrap();
+    let table = &mut tournament.current().unwrap()[table];
+    table
+        .players
+        .retain(|player_id| *player_id != form.player_id);
+    table.no_shows.push(form.player_id);
+
+    if table.players.len() <= 1 {
+        table.advanced = table.players.clone();
+        table.done = true;
+    }
+
+    next_round_if_done(&mut context, id);
+    Ok("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::tests::result;
+    use crate::storage::MemoryStorage;
+    use crate::user::User;
+
+    fn players(tables: &[Table]) -> Vec<Vec<u32>> {
+        tables.iter().map(|table| table.players.clone()).collect()
+    }
+
+    // A context with players 1 to `entrants` registered to tournament 1.
+    fn running(entrants: u32, table_size: usize, advance: usize) -> Uno {
+        let mut context = Uno::new(Box::new(MemoryStorage::new()));
+        let mut tournament = Tournament::new("Cup".to_string(), 1, table_size, advance);
+
+        for id in 1..=entrants {
+            context.users.insert(id, User::new(format!("p{}", id), id));
+            tournament.entrants.push(id);
+        }
+
+        tournament.state = TournamentState::Running;
+        context.tournaments.insert(1, tournament);
+        seat_round(&mut context, 1, (1..=entrants).collect());
+        context
+    }
+
+    // Ends the round at a table with the players finishing in `order`.
+    fn finish(context: &mut Uno, room_id: u32, order: &[u32]) {
+        let record = MatchRecord {
+            room_id,
+            ended_at: 0,
+            players: order
+                .iter()
+                .enumerate()
+                .map(|(place, player_id)| result(*player_id, place as u32 + 1))
+                .collect(),
+        };
+
+        on_round_end(context, room_id, &record);
+    }
+
+    fn round(context: &Uno, round: usize) -> Vec<Table> {
+        context.tournaments[&1].rounds[round].clone()
+    }
+
+    #[test]
+    fn tables_are_dealt_in_a_snake() {
+        let tournament = Tournament::new("Cup".to_string(), 1, 4, 2);
+        let tables = tournament.draw_tables(&(1..=10).collect::<Vec<u32>>());
+
+        assert_eq!(
+            players(&tables),
+            vec![vec![1, 6, 7], vec![2, 5, 8], vec![3, 4, 9, 10]]
+        );
+        assert_eq!(
+            players(&tournament.draw_tables(&[1, 2, 3, 4])),
+            vec![vec![1, 2, 3, 4]]
+        );
+    }
+
+    #[test]
+    fn every_table_knocks_out_someone() {
+        let tournament = Tournament::new("Cup".to_string(), 1, 4, 3);
+        let table = |players: Vec<u32>| Table {
+            players,
+            ..Table::default()
+        };
+
+        assert_eq!(tournament.places(&table(vec![1, 2, 3, 4])), 3);
+        assert_eq!(tournament.places(&table(vec![1, 2, 3])), 2);
+        assert_eq!(tournament.places(&table(vec![1, 2])), 1);
+    }
+
+    #[test]
+    fn players_are_seeded_by_rating() {
+        let mut context = Uno::new(Box::new(MemoryStorage::new()));
+        context
+            .tournaments
+            .insert(1, Tournament::new("Cup".to_string(), 1, 2, 1));
+        let mut record = MatchRecord {
+            room_id: 0,
+            ended_at: 0,
+            players: vec![result(4, 1), result(3, 2), result(2, 3), result(1, 4)],
+        };
+        context.ratings.rate(&mut record, |_| true);
+
+        seat_round(&mut context, 1, vec![1, 2, 3, 4]);
+        assert_eq!(players(&round(&context, 0)), vec![vec![4, 1], vec![3, 2]]);
+    }
+
+    #[test]
+    fn a_lone_player_gets_a_bye() {
+        let mut context = running(3, 2, 1);
+        let first = round(&context, 0);
+
+        assert_eq!(players(&first), vec![vec![1], vec![2, 3]]);
+        assert_eq!(first[0].room_id, None);
+        assert_eq!(first[0].advanced, vec![1]);
+        assert!(first[0].done);
+
+        finish(&mut context, first[1].room_id.unwrap(), &[3, 2]);
+        assert_eq!(players(&round(&context, 1)), vec![vec![1, 3]]);
+    }
+
+    #[test]
+    fn the_top_finishers_advance_to_the_final() {
+        let mut context = running(5, 4, 2);
+        let first = round(&context, 0);
+        assert_eq!(players(&first), vec![vec![1, 4, 5], vec![2, 3]]);
+
+        // A round in another room doesn't count.
+        finish(&mut context, 999, &[5, 4, 1]);
+        assert!(round(&context, 0).iter().all(|table| !table.done));
+
+        finish(&mut context, first[0].room_id.unwrap(), &[5, 1, 4]);
+        assert_eq!(round(&context, 0)[0].advanced, vec![5, 1]);
+        assert_eq!(context.tournaments[&1].rounds.len(), 1);
+
+        finish(&mut context, first[1].room_id.unwrap(), &[3, 2]);
+        assert_eq!(round(&context, 0)[1].advanced, vec![3]);
+
+        let last = round(&context, 1);
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].players.len(), 3);
+
+        finish(&mut context, last[0].room_id.unwrap(), &[1, 5, 3]);
+        let tournament = &context.tournaments[&1];
+        assert_eq!(tournament.state, TournamentState::Finished);
+        assert_eq!(tournament.winner, Some(1));
+        assert_eq!(tournament.rounds.len(), 2);
+    }
+
+    #[test]
+    fn only_the_first_round_at_a_table_counts() {
+        let mut context = running(4, 2, 1);
+        let room_id = round(&context, 0)[0].room_id.unwrap();
+
+        finish(&mut context, room_id, &[1, 4]);
+        finish(&mut context, room_id, &[4, 1]);
+        assert_eq!(round(&context, 0)[0].advanced, vec![1]);
+    }
+}
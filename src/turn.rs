@@ -0,0 +1,278 @@
+use crate::card::Card;
+
+// How the turn moves on after a card or an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Advance {
+    // The next player in play.
+    Next,
+    // Passes over the next player to the one after.
+    Skip,
+    // The same player goes again.
+    Stay,
+}
+
+// The seats around the table as far as turn order goes. Seats out of play,
+// empty hands and players knocked out of the round, are passed over.
+// `direction` true goes up the seats, false down.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TurnOrder {
+    pub in_play: Vec<bool>,
+    pub turn: usize,
+    pub direction: bool,
+}
+
+impl TurnOrder {
+    fn step(&self, seat: usize) -> usize {
+        let seats = self.in_play.len();
+
+        if self.direction {
+            (seat + 1) % seats
+        } else {
+            (seat + seats - 1) % seats
+        }
+    }
+
+    // The next seat in play after `seat`. With nobody else in play it comes
+    // back around to `seat`.
+    pub fn seat_after(&self, seat: usize) -> usize {
+        let mut next = self.step(seat);
+
+        for _ in 0..self.in_play.len() {
+            if self.in_play[next] {
+                break;
+            }
+
+            next = self.step(next);
+        }
+
+        next
+    }
+
+    pub fn following(&self) -> usize {
+        self.seat_after(self.turn)
+    }
+
+    pub fn players(&self) -> usize {
+        self.in_play.iter().filter(|in_play| **in_play).count()
+    }
+
+    pub fn advance(&mut self, advance: Advance) {
+        self.turn = match advance {
+            Advance::Next => self.following(),
+            Advance::Skip => self.seat_after(self.following()),
+            Advance::Stay => self.turn,
+        };
+    }
+}
+
+// How the turn moves on once `card` is played, with `players` in play
+// before it. Reverse turns the direction around where the card's effects
+// are applied; with only two players that leaves the same player to go
+// again, as a skip. Reverse draw cards still hand the penalty on. Whoever
+// emptied their hand doesn't go again.
+pub fn after_play(card: &Card, players: usize, emptied: bool) -> Advance {
+    let advance = match card {
+        Card::Stop(_) => Advance::Skip,
+        Card::Reverse(_) if players == 2 => Advance::Skip,
+        Card::SkipEveryone(_) => Advance::Stay,
+        _ => Advance::Next,
+    };
+
+    match advance {
+        Advance::Stay if emptied => Advance::Next,
+        advance => advance,
+    }
+}
+
+// What the first card turned up does to the first player, seat 0, or None
+// when it goes under and another card is turned up. A Stop skips them. A
+// Reverse turns the direction around so the dealer, the seat before them,
+// starts; with two players that is the same as a skip. A draw card leaves
+// them the penalty. Wild and other special cards are turned down.
+pub fn opening(card: &Card) -> Option<Advance> {
+    match card {
+        Card::Number(_, _) | Card::DiscardAll(_) | Card::Plus2(_) | Card::DrawFive(_) => {
+            Some(Advance::Stay)
+        }
+        Card::Stop(_) | Card::Reverse(_) => Some(Advance::Next),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{flip, no_mercy, CardColor};
+    use crate::house::{self, CardDef};
+
+    fn order(in_play: &[bool], turn: usize, direction: bool) -> TurnOrder {
+        TurnOrder {
+            in_play: in_play.to_vec(),
+            turn,
+            direction,
+        }
+    }
+
+    fn advanced(mut order: TurnOrder, advance: Advance) -> usize {
+        order.advance(advance);
+        order.turn
+    }
+
+    #[test]
+    fn next_goes_down_the_seats_by_default() {
+        let seats = [true; 4];
+        assert_eq!(advanced(order(&seats, 2, false), Advance::Next), 1);
+        assert_eq!(advanced(order(&seats, 1, false), Advance::Next), 0);
+        assert_eq!(advanced(order(&seats, 0, false), Advance::Next), 3);
+    }
+
+    #[test]
+    fn next_goes_up_the_seats_the_other_way() {
+        let seats = [true; 4];
+        assert_eq!(advanced(order(&seats, 0, true), Advance::Next), 1);
+        assert_eq!(advanced(order(&seats, 2, true), Advance::Next), 3);
+        assert_eq!(advanced(order(&seats, 3, true), Advance::Next), 0);
+    }
+
+    #[test]
+    fn skip_passes_over_one_player() {
+        let seats = [true; 4];
+        assert_eq!(advanced(order(&seats, 0, true), Advance::Skip), 2);
+        assert_eq!(advanced(order(&seats, 3, true), Advance::Skip), 1);
+        assert_eq!(advanced(order(&seats, 0, false), Advance::Skip), 2);
+        assert_eq!(advanced(order(&seats, 1, false), Advance::Skip), 3);
+    }
+
+    #[test]
+    fn skip_with_two_players_comes_back() {
+        let seats = [true; 2];
+        for direction in [true, false] {
+            for turn in 0..2 {
+                assert_eq!(
+                    advanced(order(&seats, turn, direction), Advance::Skip),
+                    turn
+                );
+                assert_eq!(
+                    advanced(order(&seats, turn, direction), Advance::Next),
+                    1 - turn
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stay_keeps_the_turn() {
+        for turn in 0..3 {
+            assert_eq!(advanced(order(&[true; 3], turn, true), Advance::Stay), turn);
+        }
+    }
+
+    #[test]
+    fn seats_out_of_play_are_passed_over() {
+        let seats = [true, false, false, true, true];
+        assert_eq!(advanced(order(&seats, 0, true), Advance::Next), 3);
+        assert_eq!(advanced(order(&seats, 4, true), Advance::Next), 0);
+        assert_eq!(advanced(order(&seats, 3, false), Advance::Next), 0);
+        assert_eq!(advanced(order(&seats, 0, true), Advance::Skip), 4);
+        assert_eq!(advanced(order(&seats, 3, false), Advance::Skip), 4);
+    }
+
+    #[test]
+    fn the_current_seat_can_be_out_of_play() {
+        let seats = [true, false, true];
+        assert_eq!(advanced(order(&seats, 1, true), Advance::Next), 2);
+        assert_eq!(advanced(order(&seats, 1, false), Advance::Next), 0);
+    }
+
+    #[test]
+    fn alone_in_play_the_turn_comes_back() {
+        let seats = [false, true, false];
+        assert_eq!(advanced(order(&seats, 1, true), Advance::Next), 1);
+        assert_eq!(advanced(order(&seats, 1, false), Advance::Skip), 1);
+    }
+
+    #[test]
+    fn players_counts_seats_in_play() {
+        assert_eq!(order(&[true, false, true, true], 0, true).players(), 3);
+        assert_eq!(order(&[false, false], 0, true).players(), 0);
+        assert_eq!(order(&[true; 2], 0, true).following(), 1);
+    }
+
+    #[test]
+    fn every_card_moves_the_turn_on() {
+        let stays = |card: &Card| matches!(card, Card::SkipEveryone(_));
+        let skips = |card: &Card, players: usize| {
+            matches!(card, Card::Stop(_)) || (matches!(card, Card::Reverse(_)) && players == 2)
+        };
+        let dark = flip()
+            .into_iter()
+            .map(|(_, dark)| dark)
+            .collect::<Vec<Card>>();
+        let house = house::deck(&[CardDef {
+            name: "House".to_string(),
+            colored: true,
+            copies: 1,
+            effects: vec![],
+        }]);
+
+        for card in [
+            no_mercy(),
+            dark,
+            house,
+            vec![Card::Custom("Wild".to_string(), None)],
+        ]
+        .concat()
+        {
+            for players in 2..=6 {
+                for emptied in [false, true] {
+                    let expected = if skips(&card, players) {
+                        Advance::Skip
+                    } else if stays(&card) && !emptied {
+                        Advance::Stay
+                    } else {
+                        Advance::Next
+                    };
+
+                    assert_eq!(
+                        after_play(&card, players, emptied),
+                        expected,
+                        "{:?} with {} players",
+                        card,
+                        players
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_draw_cards_pass_the_penalty_on() {
+        assert_eq!(after_play(&Card::ReversePlus4, 2, false), Advance::Next);
+    }
+
+    #[test]
+    fn only_plain_colored_cards_open() {
+        for card in [
+            no_mercy(),
+            flip().into_iter().flat_map(|(l, d)| [l, d]).collect(),
+        ]
+        .concat()
+        {
+            let expected = match card {
+                Card::Number(_, _) | Card::DiscardAll(_) | Card::Plus2(_) | Card::DrawFive(_) => {
+                    Some(Advance::Stay)
+                }
+                Card::Stop(_) | Card::Reverse(_) => Some(Advance::Next),
+                _ => None,
+            };
+
+            assert_eq!(opening(&card), expected, "{:?}", card);
+        }
+
+        assert_eq!(opening(&Card::Custom("Wild".to_string(), None)), None);
+        assert_eq!(
+            opening(&Card::Custom("Red".to_string(), Some(CardColor::Red))),
+            None
+        );
+    }
+}
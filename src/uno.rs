@@ -1,14 +1,21 @@
 use crate::card::{all, Card};
-use crate::room::Room;
+use crate::event::{Event, EventKind};
+use crate::house::CardDef;
+use crate::matchmaking::Queue;
+use crate::rating::Ratings;
+use crate::room::{Room, RuleSet};
+use crate::stats::{self, MatchRecord};
+use crate::storage::{Storage, StorageResult};
+use crate::strategy::Strategy;
+use crate::tournament::{self, Tournament};
 use crate::user::User;
 use actix_session::Session;
 use actix_web::HttpRequest;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap, HashSet},
     fmt::{self, Debug},
 };
 
-#[derive(Debug, Clone)]
 pub struct Uno {
     pub users: HashMap<u32, User>,
     pub rooms: HashMap<u32, Room>,
@@ -16,10 +23,22 @@ pub struct Uno {
     pub room_index: u32,
     pub subscribers: HashMap<u32, Vec<UnoSocket>>,
     pub cards: Vec<Card>,
+    pub storage: Box<dyn Storage>,
+    pub strategies: HashMap<u32, Box<dyn Strategy>>,
+    // Rooms whose bots are playing, see bot::run.
+    pub bots_running: HashSet<u32>,
+    pub matches: Vec<MatchRecord>,
+    pub ratings: Ratings,
+    pub queue: Queue,
+    pub queue_subscribers: HashMap<u32, Vec<UnoSocket>>,
+    pub matched: HashMap<u32, u32>,
+    pub tournaments: HashMap<u32, Tournament>,
+    pub tournament_index: u32,
+    pub house: Vec<CardDef>,
 }
 
 impl Uno {
-    pub fn new() -> Uno {
+    pub fn new(storage: Box<dyn Storage>) -> Uno {
         Uno {
             users: HashMap::new(),
             rooms: HashMap::new(),
@@ -27,8 +46,146 @@ impl Uno {
             room_index: 0,
             subscribers: HashMap::new(),
             cards: all(),
+            storage,
+            strategies: HashMap::new(),
+            bots_running: HashSet::new(),
+            matches: vec![],
+            ratings: Ratings::default(),
+            queue: Queue::default(),
+            queue_subscribers: HashMap::new(),
+            matched: HashMap::new(),
+            tournaments: HashMap::new(),
+            tournament_index: 0,
+            house: vec![],
         }
     }
+
+    pub fn restore(storage: Box<dyn Storage>) -> StorageResult<Uno> {
+        let mut uno = Uno::new(storage);
+
+        for user in uno.storage.users()? {
+            uno.user_index = uno.user_index.max(user.id);
+            uno.users.insert(user.id, user);
+        }
+
+        for (room_id, room) in uno.storage.rooms()? {
+            uno.room_index = uno.room_index.max(room_id);
+            // Bots are only saved with their rooms, but share the user ids.
+            for player in room.players.iter() {
+                uno.user_index = uno.user_index.max(player.id);
+            }
+            uno.rooms.insert(room_id, room);
+        }
+
+        uno.matches = uno.storage.matches()?;
+        uno.ratings = Ratings::restore(&uno.matches);
+
+        for (tournament_id, tournament) in uno.storage.tournaments()? {
+            uno.tournament_index = uno.tournament_index.max(tournament_id);
+            uno.tournaments.insert(tournament_id, tournament);
+        }
+
+        Ok(uno)
+    }
+
+    pub fn save_user(&mut self, player_id: u32) {
+        if let Some(user) = self.users.get(&player_id) {
+            if let Err(error) = self.storage.save_user(user) {
+                eprintln!("Could not save user {}: {}", player_id, error);
+            }
+        }
+    }
+
+    pub fn save_room(&mut self, room_id: u32, events: Vec<Event>) {
+        let ended = match self.rooms.get_mut(&room_id) {
+            Some(room) => room.end_round(),
+            None => false,
+        };
+
+        if let Some(room) = self.rooms.get(&room_id) {
+            if let Err(error) = self.storage.save_room(room_id, room) {
+                eprintln!("Could not save room {}: {}", room_id, error);
+            }
+        }
+
+        for event in events {
+            if let Err(error) = self.storage.append_event(room_id, &event) {
+                eprintln!("Could not log event for room {}: {}", room_id, error);
+            }
+        }
+
+        if ended {
+            self.record_match(room_id);
+        }
+    }
+
+    fn record_match(&mut self, room_id: u32) {
+        let events = match self.storage.events(room_id) {
+            Ok(events) => events,
+            Err(error) => {
+                eprintln!("Could not read events of room {}: {}", room_id, error);
+                return;
+            }
+        };
+
+        let mut record = match stats::record(room_id, &self.rooms[&room_id], &events) {
+            Some(record) => record,
+            None => return,
+        };
+
+        let users = &self.users;
+        self.ratings
+            .rate(&mut record, |player_id| users.contains_key(&player_id));
+
+        if let Err(error) = self.storage.save_match(&record) {
+            eprintln!("Could not save match of room {}: {}", room_id, error);
+        }
+
+        tournament::on_round_end(self, room_id, &record);
+        self.matches.push(record);
+    }
+
+    pub fn save_tournament(&mut self, tournament_id: u32) {
+        if let Some(tournament) = self.tournaments.get(&tournament_id) {
+            if let Err(error) = self.storage.save_tournament(tournament_id, tournament) {
+                eprintln!("Could not save tournament {}: {}", tournament_id, error);
+            }
+        }
+    }
+
+    pub fn create_room(&mut self, rules: RuleSet) -> u32 {
+        self.room_index += 1;
+        let room_id = self.room_index;
+        let mut room = Room::new();
+        room.rules = rules.clone();
+        let seed = room.seed;
+        self.rooms.insert(room_id, room);
+        self.save_room(
+            room_id,
+            vec![
+                Event::new(0, EventKind::Seed { seed }),
+                Event::new(0, EventKind::Rules { rules }),
+            ],
+        );
+        room_id
+    }
+
+    pub fn join(&mut self, room_id: u32, user: User) {
+        let player_id = user.id;
+        let join = EventKind::Join {
+            name: user.name.clone(),
+            bot: None,
+        };
+
+        if let Some(room) = self.rooms.get_mut(&room_id) {
+            room.seat(user);
+            self.save_room(room_id, vec![Event::new(player_id, join)]);
+        }
+    }
+
+    pub fn sockets(&self, room_id: u32) -> Vec<UnoSocket> {
+        self.subscribers.get(&room_id).cloned().unwrap_or_default()
+    }
 }
 
 #[derive(Clone)]
@@ -42,6 +199,34 @@ impl Debug for UnoSocket {
     }
 }
 
+pub async fn notify(sockets: Vec<UnoSocket>) {
+    broadcast(sockets, "update").await;
+}
+
+pub async fn broadcast(sockets: Vec<UnoSocket>, text: &str) {
+    for mut socket in sockets {
+        let _ = socket.session.text(text.to_string()).await;
+    }
+}
+
+// Finds the account behind the session, creating it on first use.
+pub fn get_user(context: &mut Uno, username: &str, session: &Session) -> User {
+    let mut player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
+
+    if player_id == 0 {
+        context.user_index += 1;
+        player_id = context.user_index;
+        session.insert("player_id", player_id).unwrap();
+    }
+
+    if let Entry::Vacant(entry) = context.users.entry(player_id) {
+        entry.insert(User::new(username.to_string(), player_id));
+        context.save_user(player_id);
+    }
+
+    context.users.get(&player_id).unwrap().clone()
+}
+
 pub fn room_and_player(req: HttpRequest, session: Session) -> (u32, u32) {
     let room_id = req
         .match_info()
@@ -52,3 +237,24 @@ pub fn room_and_player(req: HttpRequest, session: Session) -> (u32, u32) {
     let player_id = session.get::<u32>("player_id").unwrap().unwrap_or(0);
     (room_id, player_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::strategy::BotKind;
+
+    #[test]
+    fn new_users_never_get_a_seated_bots_id() {
+        let mut storage = MemoryStorage::new();
+        let mut room = Room::new();
+        room.seat(User::new("a".to_string(), 1));
+        room.seat(User::bot("Bot 2".to_string(), 2, BotKind::Heuristic));
+        storage.save_user(&User::new("a".to_string(), 1)).unwrap();
+        storage.save_room(1, &room).unwrap();
+
+        let uno = Uno::restore(Box::new(storage)).unwrap();
+
+        assert_eq!(uno.user_index, 2);
+    }
+}
@@ -1,12 +1,17 @@
 use crate::card::Card;
-use serde::Serialize;
+use crate::strategy::BotKind;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct User {
     pub name: String,
     pub id: u32,
     pub hand: Vec<(u8, Card)>,
     pub drawed: bool,
+    #[serde(default)]
+    pub bot: Option<BotKind>,
+    #[serde(default)]
+    pub team: Option<u8>,
 }
 
 impl User {
@@ -16,6 +21,15 @@ impl User {
             id,
             hand: vec![],
             drawed: false,
+            bot: None,
+            team: None,
+        }
+    }
+
+    pub fn bot(name: String, id: u32, kind: BotKind) -> User {
+        User {
+            bot: Some(kind),
+            ..User::new(name, id)
         }
     }
 }